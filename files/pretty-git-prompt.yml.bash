@@ -35,12 +35,66 @@ values:
       # example: 'upstream/master'
       # if omitted look for remotely tracked branch usually set up with:
       #   git branch --set-upstream-to
+      # <REMOTE> stands for the first of 'remotes' which has the branch and HEAD
+      # for the default branch of the remote, 'default_branch' is the same as
+      # '<REMOTE>/HEAD'
+      # it can also be a list of branches, or a glob matching remote branches such as
+      # '*/main' or 'origin/release/*': 'values' are displayed for each of them
       # remote_branch: ''
+      # displayed between the values of different remote branches (optional,
+      # defaults to ''), type string
+      # group_separator: ' '
+      # the maximal number of remote branches displayed (optional), type int
+      # max_items: 3
+      # remotes tried for <REMOTE> in this order (optional, defaults to
+      # ['upstream', 'origin']), type list of strings
+      # remotes: ['upstream', 'origin']
+      # names of the default branch tried when HEAD of a remote is not set, which
+      # is fixed with 'git remote set-head <REMOTE> --auto' (optional, defaults to
+      # ['main', 'master']), type list of strings
+      # default_branch_names: ['main', 'master']
+      # which remote branch is followed when 'remote_branch' is omitted (optional,
+      # defaults to 'upstream'), type string
+      #  * upstream -- the one the branch pulls from
+      #  * push -- the one 'git push' updates, which differs in a triangular workflow:
+      #            branch.<name>.pushRemote, remote.pushDefault and push.default
+      #            are respected
+      # target: 'upstream'
+      # stop counting commits ahead and behind after this many (optional), type int
+      # the count is displayed as e.g. '999+' then; keeps the prompt fast when the
+      # branches diverged a lot
+      # walk_limit: 999
+      # remember the counts in the git directory, so they're not counted again
+      # until one of the branches moves (optional, defaults to false), type bool
+      # cache: false
       # display the remote even if there is no difference with current branch (required), type bool
       display_if_uptodate: true
       # what <REMOTE_FIRST_LETTER> renders as when there is no remote
       # counterpart (optional, defaults to '_'), type string
       # no_remote_placeholder: '_'
+      # how the checked out commit is named when HEAD is detached (optional,
+      # defaults to 'hash'), type string or list of strings
+      # when it's a list, the first name which can be figured out is used:
+      #  * tag -- a tag pointing at the commit
+      #  * branch -- the nearest branch containing the commit, e.g. 'master~3'
+      #  * hash -- abbreviated hash of the commit, used when nothing else is found
+      # detached_head: ['tag', 'branch', 'hash']
+      # minimal length of the abbreviated hash (optional, defaults to 7), type int
+      # the hash gets longer when it wouldn't be unique in the repository
+      # hash_length: 7
+      # what <DETACHED> renders as when HEAD is detached (optional, defaults
+      # to ''), type string
+      # detached_marker: '➦'
+      # formatting used instead of 'values' when the branch doesn't track a remote
+      # branch or when 'remote_branch' doesn't exist (optional), type dict
+      # no_upstream_format:
+      #   pre_format: '<LOCAL_BRANCH>'
+      #   post_format: ' (local)'
+      # formatting used instead of 'values' when the tracked remote branch was
+      # deleted, e.g. after its pull request was merged (optional), type dict
+      # gone_format:
+      #   pre_format: '<LOCAL_BRANCH>'
+      #   post_format: ' (gone)'
       pre_format: ''
       post_format: ''
       # values which can be displayed as part of 'remote_difference'
@@ -54,6 +108,25 @@ values:
           #  * <REMOTE_FIRST_LETTER> will be replaced with the first letter of
           #    a remote name, or with 'no_remote_placeholder' if the branch has
           #    no remote counterpart
+          #  * <DETACHED> will be replaced with 'detached_marker' when HEAD is
+          #    detached
+          #  * <UPSTREAM_STATE> will be replaced with 'gone' when the tracked
+          #    remote branch was deleted, with 'none' when there is no remote
+          #    branch and with nothing otherwise
+          # <LOCAL_BRANCH> and <REMOTE_BRANCH> can be rewritten (optional), type list
+          # every rule is a regular expression and its replacement, applied in
+          # order; $1 in the replacement stands for the first group
+          # rewrite:
+          #   - pattern: '^feature/'
+          #     replacement: 'f/'
+          #   - pattern: '^.*(JIRA-[0-9]+).*$'
+          #     replacement: '$1'
+          # and shortened to a maximal number of characters (optional), type int
+          # max_length: 20
+          # what replaces the part which is cut off (optional, defaults to '…'), type string
+          # ellipsis: '…'
+          # which side is cut off, 'right' or 'left' (optional, defaults to 'right'), type string
+          # truncate: 'right'
           pre_format: '\[\e[38;5;4m\]<LOCAL_BRANCH>'
           post_format: '\[\e[0m\]'
           # the number of files present locally which are missing in remote repo
@@ -75,8 +148,10 @@ values:
       display: surrounded
       pre_format: '│'
       post_format: ''
+      # the default branch of remote 'upstream'
     - type: remote_difference
-      remote_branch: 'upstream/master'
+      remote_branch: 'default_branch'
+      remotes: ['upstream']
       display_if_uptodate: false
       pre_format: ''
       post_format: ''
@@ -107,6 +182,9 @@ values:
       pre_format: '\[\e[38;5;2m\]▶'
       post_format: '\[\e[0m\]'
       # during merge, rebase, or others, the numbers files which conflict
+      # <FILES> lists names of the first max_files of them (3 by default);
+      # <BOTH_MODIFIED>, <ADDED_BY_BOTH>, <DELETED_BY_US> and <DELETED_BY_THEM>
+      # count them by the kind of the conflict
     - type: conflicts
       pre_format: '\[\e[38;5;226m\]✖'
       post_format: '\[\e[0m\]'
@@ -114,7 +192,9 @@ values:
       display: surrounded
       pre_format: '│'
       post_format: ''
-      # show number of items in stash
+      # the number of items in the stash; <AGE> is how old the newest one is and
+      # <ON_BRANCH> is replaced with on_branch_marker when any of them was created
+      # on the current branch; current_branch_only: true counts only those
     - type: stash
       pre_format: '\[\e[38;5;226m\]☐'
       post_format: '\[\e[0m\]'
@@ -1,11 +1,19 @@
+use std::env;
 use std::fmt;
-use std::collections::HashMap;
+use std::fs;
+use std::path::{Path,PathBuf};
+use std::process;
+use std::str;
+use std::collections::{BinaryHeap,HashMap,HashSet};
 use std::cell::RefCell;
 
-use constants::{CHANGED_KEY,NEW_KEY,STAGED_KEY,CONFLICTS_KEY};
+use constants::{CHANGED_KEY,NEW_KEY,STAGED_KEY,CONFLICTS_KEY,CACHE_FILE_NAME,CACHE_MAX_ENTRIES,
+                LFS_LOCK_CACHE,LFS_POINTER_MAX_SIZE,LFS_POINTER_PREFIX};
 
 use git2::*;
 
+use util::gob_map_keys;
+
 
 #[derive(Debug, Clone)]
 pub struct RemoteBranch {
@@ -18,6 +26,173 @@ pub struct RemoteBranch {
     pub remote_name: String,
 }
 
+// the nearest tag reachable from HEAD, as `git describe` finds it
+#[derive(Debug, Clone)]
+pub struct Description {
+    pub tag: String,
+    // the number of commits on top of the tag
+    pub distance: usize,
+    // abbreviated hash of HEAD
+    pub hash: String,
+}
+
+// ways to name the checked out commit when HEAD is detached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetachedName {
+    // the nearest branch containing the commit: master~3 (`git name-rev`)
+    Branch,
+    // a tag pointing at the commit
+    Tag,
+    // abbreviated hash of the commit
+    Hash,
+}
+
+impl DetachedName {
+    pub fn from_name(name: &str) -> Option<DetachedName> {
+        match name {
+            "branch" => Some(DetachedName::Branch),
+            "tag" => Some(DetachedName::Tag),
+            "hash" => Some(DetachedName::Hash),
+            _ => None,
+        }
+    }
+}
+
+// where the current branch forked off a base branch
+#[derive(Debug, Clone)]
+pub struct ForkPoint {
+    // commits on the branch since the fork point
+    pub ahead: usize,
+    // commits on the base branch since the fork point
+    pub behind: usize,
+    // commit time of the merge base, seconds since the epoch
+    pub time: i64,
+}
+
+// the checked out commit
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    // the first line of the commit message
+    pub subject: String,
+    pub author: String,
+    // commit time, seconds since the epoch
+    pub time: i64,
+    pub is_merge: bool,
+}
+
+// submodules which need attention
+#[derive(Debug, Clone)]
+pub struct SubmoduleSummary {
+    // with changes inside the submodule's work tree
+    pub modified: usize,
+    // registered, but not checked out
+    pub uninitialized: usize,
+    // checked out at a different commit than the one recorded in the index
+    pub out_of_sync: usize,
+}
+
+// the work trees of the repository
+#[derive(Debug, Clone)]
+pub struct WorktreeInfo {
+    // name of the linked work tree we're in, None in the main one
+    pub name: Option<String>,
+    // the number of the other work trees, including the main one
+    pub others: usize,
+    pub locked: usize,
+    pub prunable: usize,
+}
+
+// characteristics of the repository which make git behave differently
+#[derive(Debug, Clone, Default)]
+pub struct RepoFlags {
+    pub shallow: bool,
+    pub sparse: bool,
+    // objects are fetched lazily from a promisor remote
+    pub partial: bool,
+    pub bare: bool,
+    // the current directory is inside the git directory of a repository with a work tree
+    pub in_git_dir: bool,
+}
+
+// state of the files stored in Git LFS
+#[derive(Debug, Clone)]
+pub struct LfsSummary {
+    // LFS files whose content is not in the work tree, only the pointer to it
+    pub pointers: usize,
+    // files locked by you, according to the lock cache of git-lfs
+    pub locked: usize,
+}
+
+// which sides of a merge changed a conflicting file, the way `git status` puts it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictKind {
+    BothModified,
+    AddedByBoth,
+    DeletedByUs,
+    DeletedByThem,
+    AddedByUs,
+    AddedByThem,
+    BothDeleted,
+}
+
+impl ConflictKind {
+    // from the stages present in the index: the common ancestor, ours and theirs
+    fn from_stages(ancestor: bool, ours: bool, theirs: bool) -> ConflictKind {
+        match (ancestor, ours, theirs) {
+            (true, true, true) => ConflictKind::BothModified,
+            (false, true, true) => ConflictKind::AddedByBoth,
+            (true, false, true) => ConflictKind::DeletedByUs,
+            (true, true, false) => ConflictKind::DeletedByThem,
+            (false, true, false) => ConflictKind::AddedByUs,
+            (false, false, true) => ConflictKind::AddedByThem,
+            _ => ConflictKind::BothDeleted,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            ConflictKind::BothModified => "both modified",
+            ConflictKind::AddedByBoth => "both added",
+            ConflictKind::DeletedByUs => "deleted by us",
+            ConflictKind::DeletedByThem => "deleted by them",
+            ConflictKind::AddedByUs => "added by us",
+            ConflictKind::AddedByThem => "added by them",
+            ConflictKind::BothDeleted => "both deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+// an item in the stash
+#[derive(Debug, Clone)]
+pub struct StashInfo {
+    // the branch the stash was created on, None when HEAD was detached
+    pub branch: Option<String>,
+    // seconds since the epoch
+    pub time: i64,
+}
+
+impl StashInfo {
+    // the branch from a stash message: `WIP on <branch>: ...` or `On <branch>: ...`
+    fn parse_branch(message: &str) -> Option<String> {
+        let rest = if let Some(r) = message.strip_prefix("WIP on ") {
+            r
+        } else {
+            message.strip_prefix("On ")?
+        };
+        let branch = rest.split(": ").next()?;
+        if branch == "(no branch)" {
+            return None;
+        }
+        Some(branch.to_string())
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Cache {
     current_branch_name: RefCell<Option<String>>,
@@ -33,13 +208,33 @@ struct Cache {
 pub struct DemoData {
     pub repository_state: String,
     pub branch_name: String,
+    // branch_name is what the detached HEAD is named
+    pub detached: bool,
     pub tag: Option<String>,
+    pub description: Option<Description>,
+    pub fork_point: Option<ForkPoint>,
+    pub head_commit: Option<CommitInfo>,
+    pub unpushed_tags: Vec<String>,
+    pub submodules: Option<SubmoduleSummary>,
+    pub worktree: Option<WorktreeInfo>,
+    pub flags: RepoFlags,
+    // URL of the remote
+    pub remote_url: Option<String>,
+    pub config: HashMap<String, String>,
+    pub lfs: Option<LfsSummary>,
+    pub conflicts: Vec<Conflict>,
+    pub repo_name: String,
+    // the current directory relative to the root of the work tree, components separated by '/'
+    pub relative_dir: String,
     // the branch has a remote counterpart
     pub remote_name: Option<String>,
+    // without a remote counterpart: the branch is set up to track one
+    pub upstream_gone: bool,
     pub ahead: usize,
     pub behind: usize,
     pub file_statuses: HashMap<String, u32>,
-    pub stash_count: u16,
+    // the newest first
+    pub stashes: Vec<StashInfo>,
 }
 
 impl DemoData {
@@ -47,12 +242,27 @@ impl DemoData {
         DemoData{
             repository_state: String::new(),
             branch_name: branch_name.to_string(),
+            detached: false,
             tag: None,
+            description: None,
+            fork_point: None,
+            head_commit: None,
+            unpushed_tags: vec!(),
+            submodules: None,
+            worktree: None,
+            flags: RepoFlags::default(),
+            remote_url: Some(String::from("git@github.com:TomasTomecek/pretty-git-prompt.git")),
+            config: HashMap::new(),
+            lfs: None,
+            conflicts: vec!(),
+            repo_name: String::from("pretty-git-prompt"),
+            relative_dir: String::new(),
             remote_name: Some(String::from("origin")),
+            upstream_gone: false,
             ahead: 0,
             behind: 0,
             file_statuses: HashMap::new(),
-            stash_count: 0,
+            stashes: vec!(),
         }
     }
 }
@@ -62,6 +272,8 @@ pub struct Backend {
     // there is no repository when the prompt is rendered from demo data
     repo: Option<Repository>,
     demo: Option<DemoData>,
+    // submodules are left out of the status, their work trees are not scanned then
+    exclude_submodules: bool,
     pub debug: bool,
 }
 
@@ -73,22 +285,58 @@ impl fmt::Debug for Backend {
 }
 
 
+// which remote branch the current branch is compared against when none is configured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareTarget {
+    // the branch it pulls from: branch.<name>.merge
+    Upstream,
+    // the branch it pushes to, which differs in a triangular workflow
+    Push,
+}
+
+// whether the remote branch we compare against is there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpstreamState {
+    Tracking,
+    // the branch is set up to track a remote branch which doesn't exist anymore:
+    // `[gone]` in `git branch -vv`
+    Gone,
+    NoUpstream,
+}
+
 #[derive(Clone)]
 pub struct BranchAheadBehind {
     pub local_branch_name: Option<String>,
     pub remote_branch_name: Option<String>,
     pub remote_name: Option<String>,
+    pub upstream_state: UpstreamState,
     pub ahead: usize,
-    pub behind: usize
+    pub behind: usize,
+    // counting stopped at the walk limit, there are more commits
+    pub ahead_capped: bool,
+    pub behind_capped: bool,
 }
 
 impl BranchAheadBehind {
     fn new(l: Option<String>) -> BranchAheadBehind {
         BranchAheadBehind{ local_branch_name: l, remote_branch_name: None, remote_name: None,
-                           ahead: 0, behind: 0 }
+                           upstream_state: UpstreamState::NoUpstream, ahead: 0, behind: 0,
+                           ahead_capped: false, behind_capped: false }
     }
 }
 
+// how commits ahead and behind are counted
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    // stop counting after this many commits on either side
+    pub limit: Option<usize>,
+    // reuse the counts from earlier runs, kept in the git directory
+    pub cache: bool,
+}
+
+// (ahead, ahead_capped, behind, behind_capped)
+type Counts = (usize, bool, usize, bool);
+
 
 #[derive(Clone)]
 struct RefPair {
@@ -136,12 +384,16 @@ impl Cache {
 
 impl Backend {
     pub fn new(repo: Repository, debug: bool) -> Backend {
-        Backend{ repo: Some(repo), demo: None, debug: debug, cache: Cache::new() }
+        Backend{ repo: Some(repo), demo: None, debug: debug, cache: Cache::new(), exclude_submodules: false }
     }
 
     // a backend which answers from canned data instead of a repository
     pub fn new_demo(demo: DemoData, debug: bool) -> Backend {
-        Backend{ repo: None, demo: Some(demo), debug: debug, cache: Cache::new() }
+        Backend{ repo: None, demo: Some(demo), debug: debug, cache: Cache::new(), exclude_submodules: false }
+    }
+
+    pub fn set_exclude_submodules(&mut self, exclude: bool) {
+        self.exclude_submodules = exclude;
     }
 
     fn get_head(&self) -> Option<Reference<'_>> {
@@ -204,14 +456,11 @@ impl Backend {
                 log!(self, "Shorthand for reference is: {}", s);
                 return Some(s);
             } else if let Some(ref_name) = r.symbolic_target() {
-                let ref_name_string = ref_name.to_string();
-                log!(self, "shorthand = HEAD, links to: {}", ref_name_string);
-                let mut path: Vec<&str> = ref_name_string.split('/').collect();
-                if let Some(branch_short) = path.pop() {
-                    let s = branch_short.to_string();
-                    log!(self, "Last part of full name is: {}", s);
-                    return Some(s);
-                }
+                log!(self, "shorthand = HEAD, links to: {}", ref_name);
+                // the branch doesn't exist yet, there are no commits on it
+                let s = ref_name.trim_start_matches("refs/heads/").to_string();
+                log!(self, "Name of unborn branch is: {}", s);
+                return Some(s);
             }
         }
 
@@ -250,6 +499,126 @@ impl Backend {
         current_branch_name
     }
 
+    // HEAD points to a branch without any commits: an empty repository or an orphan branch
+    pub fn is_head_unborn(&self) -> bool {
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return false,
+        };
+        match repo.head() {
+            Ok(_) => false,
+            Err(e) => e.code() == ErrorCode::UnbornBranch,
+        }
+    }
+
+    pub fn is_head_detached(&self) -> bool {
+        if let Some(ref demo) = self.demo {
+            return demo.detached;
+        }
+        match self.repo {
+            Some(ref repo) => repo.head_detached().unwrap_or(false),
+            None => false,
+        }
+    }
+
+    // name for the commit of a detached HEAD: the first of names which can be figured out,
+    // the abbreviated hash when none can
+    pub fn get_detached_head_name(&self, names: &[DetachedName], hash_length: usize) -> Option<String> {
+        if let Some(ref demo) = self.demo {
+            return Some(demo.branch_name.clone());
+        }
+        let oid = self.get_current_branch_oid()?;
+        for name in names {
+            let found = match *name {
+                DetachedName::Branch => self.get_nearest_branch_name(oid),
+                DetachedName::Tag => self.get_tag_name(),
+                DetachedName::Hash => break,
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        self.get_abbreviated_hash(oid, hash_length)
+    }
+
+    // the branch with the fewest commits on top of oid, in the form of `git name-rev`: master~3;
+    // local branches win over remote ones with the same distance
+    fn get_nearest_branch_name(&self, oid: Oid) -> Option<String> {
+        let repo = self.repo.as_ref()?;
+        let mut nearest: Option<(usize, String)> = None;
+        for branch_type in &[BranchType::Local, BranchType::Remote] {
+            let branches = match repo.branches(Some(*branch_type)) {
+                Ok(b) => b,
+                Err(e) => {
+                    log!(self, "Can't list branches: {:?}", e);
+                    return None;
+                }
+            };
+            for (branch, _) in branches.flatten() {
+                // symbolic references such as origin/HEAD have no target
+                let tip = match branch.get().target() {
+                    Some(t) => t,
+                    None => continue,
+                };
+                // a branch further than the nearest one found so far can't win
+                let max_distance = nearest.as_ref().map(|&(d, _)| d);
+                let distance = match self.first_parent_distance(tip, oid, max_distance) {
+                    Some(d) => d,
+                    None => continue,
+                };
+                if let Ok(Some(name)) = branch.name() {
+                    nearest = Some((distance, name.to_string()));
+                }
+            }
+        }
+        log!(self, "Nearest branch containing HEAD: {:?}", nearest);
+        nearest.map(|(distance, name)| {
+            if distance == 0 { name } else { format!("{}~{}", name, distance) }
+        })
+    }
+
+    // how many first parents lead from tip to oid, like ~N of `git name-rev`; None when oid is
+    // not on the first parent line of tip within fewer than max_distance steps
+    fn first_parent_distance(&self, tip: Oid, oid: Oid, max_distance: Option<usize>) -> Option<usize> {
+        let repo = self.repo.as_ref()?;
+        let mut revwalk = match repo.revwalk() {
+            Ok(r) => r,
+            Err(e) => {
+                log!(self, "Can't walk history: {:?}", e);
+                return None;
+            }
+        };
+        if revwalk.push(tip).is_err() || revwalk.simplify_first_parent().is_err() {
+            return None;
+        }
+        for (distance, commit) in revwalk.enumerate() {
+            if max_distance.is_some_and(|m| distance >= m) {
+                return None;
+            }
+            match commit {
+                Ok(c) if c == oid => return Some(distance),
+                Ok(_) => (),
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+
+    // hash of the commit, at least length characters long, longer when needed to be unique
+    fn get_abbreviated_hash(&self, oid: Oid, length: usize) -> Option<String> {
+        let repo = self.repo.as_ref()?;
+        let short_id = match repo.find_object(oid, None).and_then(|o| o.short_id()) {
+            Ok(buf) => buf.as_str().unwrap_or_default().to_string(),
+            Err(e) => {
+                log!(self, "Can't abbreviate hash {}: {:?}", oid, e);
+                return None;
+            }
+        };
+        let full = oid.to_string();
+        let length = length.max(short_id.len()).min(full.len());
+        Some(full[..length].to_string())
+    }
+
     // name of a tag which points exactly at HEAD (`git describe --tags --exact-match`)
     pub fn get_tag_name(&self) -> Option<String> {
         if let Some(ref demo) = self.demo {
@@ -279,6 +648,133 @@ impl Backend {
         }
     }
 
+    // the nearest tag reachable from HEAD (`git describe --tags`); only annotated tags are
+    // considered with annotated_only, only tags matching the glob pattern when it's set
+    pub fn get_description(&self, annotated_only: bool, pattern: Option<&str>) -> Option<Description> {
+        if let Some(ref demo) = self.demo {
+            return demo.description.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let mut opts = DescribeOptions::new();
+        if !annotated_only {
+            opts.describe_tags();
+        }
+        if let Some(p) = pattern {
+            opts.pattern(p);
+        }
+        let description = match repo.describe(&opts) {
+            Ok(d) => d,
+            Err(e) => {
+                log!(self, "No tag reachable from HEAD: {:?}", e);
+                return None;
+            }
+        };
+        // just the name of the tag, without the distance and the hash
+        let mut format_opts = DescribeFormatOptions::new();
+        format_opts.abbreviated_size(0);
+        let tag = match description.format(Some(&format_opts)) {
+            Ok(name) => name,
+            Err(e) => {
+                log!(self, "Can't format tag name: {:?}", e);
+                return None;
+            }
+        };
+        let head_oid = self.get_current_branch_oid()?;
+        let tag_oid = match repo.revparse_single(&format!("refs/tags/{}", tag))
+                .and_then(|o| o.peel_to_commit()) {
+            Ok(c) => c.id(),
+            Err(e) => {
+                log!(self, "Can't find commit of tag {}: {:?}", tag, e);
+                return None;
+            }
+        };
+        let distance = match repo.graph_ahead_behind(head_oid, tag_oid) {
+            Ok((a, _)) => a,
+            Err(e) => {
+                log!(self, "Can't count commits since tag {}: {:?}", tag, e);
+                return None;
+            }
+        };
+        let hash = self.get_abbreviated_hash(head_oid, 0)?;
+        log!(self, "HEAD is {} commits on top of tag {}", distance, tag);
+        Some(Description{ tag, distance, hash })
+    }
+
+    // tags whose commit can't be reached from any reference matching the globs in ref_scope,
+    // e.g. `refs/remotes/*`; nothing is unpushed when there are no such references
+    pub fn get_unpushed_tags(&self, ref_scope: &[String]) -> Vec<String> {
+        if let Some(ref demo) = self.demo {
+            return demo.unpushed_tags.clone();
+        }
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return vec!(),
+        };
+        let mut scope_oids: Vec<Oid> = Vec::new();
+        for glob in ref_scope {
+            match repo.references_glob(glob) {
+                Ok(references) => {
+                    for reference in references.flatten() {
+                        if let Ok(c) = reference.peel_to_commit() {
+                            if !scope_oids.contains(&c.id()) {
+                                scope_oids.push(c.id());
+                            }
+                        }
+                    }
+                },
+                Err(e) => log!(self, "Can't look up references matching {}: {:?}", glob, e),
+            }
+        }
+        if scope_oids.is_empty() {
+            log!(self, "No references in {:?}, there is nowhere to push tags to", ref_scope);
+            return vec!();
+        }
+        let tag_names = match repo.tag_names(None) {
+            Ok(t) => t,
+            Err(e) => {
+                log!(self, "Can't list tags: {:?}", e);
+                return vec!();
+            }
+        };
+        let mut tags: Vec<(String, Oid)> = Vec::new();
+        for tag in tag_names.iter().flatten() {
+            match repo.revparse_single(&format!("refs/tags/{}", tag)).and_then(|o| o.peel_to_commit()) {
+                Ok(c) => tags.push((tag.to_string(), c.id())),
+                Err(e) => log!(self, "Can't find commit of tag {}: {:?}", tag, e),
+            }
+        }
+        if tags.is_empty() {
+            return vec!();
+        }
+        // walk from the tagged commits with everything the references reach hidden: only the
+        // commits missing from the remotes come out, a tag whose commit does is unpushed
+        let mut revwalk = match repo.revwalk() {
+            Ok(r) => r,
+            Err(e) => {
+                log!(self, "Can't walk history: {:?}", e);
+                return vec!();
+            }
+        };
+        for &(_, oid) in &tags {
+            if let Err(e) = revwalk.push(oid) {
+                log!(self, "Can't walk history from {}: {:?}", oid, e);
+            }
+        }
+        for oid in &scope_oids {
+            if let Err(e) = revwalk.hide(*oid) {
+                log!(self, "Can't hide history of {}: {:?}", oid, e);
+            }
+        }
+        let tag_oids: HashSet<Oid> = tags.iter().map(|&(_, oid)| oid).collect();
+        let unpushed_oids: HashSet<Oid> = revwalk.flatten().filter(|oid| tag_oids.contains(oid)).collect();
+        let unpushed: Vec<String> = tags.into_iter()
+            .filter(|&(_, oid)| unpushed_oids.contains(&oid))
+            .map(|(name, _)| name)
+            .collect();
+        log!(self, "Unpushed tags: {:?}", unpushed);
+        unpushed
+    }
+
     fn get_branch_remote(&self, reference: Reference) -> Option<RefPair> {
         let b = Branch::wrap(reference);
         let upstream = match b.upstream() {
@@ -329,6 +825,87 @@ impl Backend {
                       branch_name: branch_name.to_string(), oid: oid })
     }
 
+    // the current branch has an upstream set in config: branch.<name>.remote and
+    // branch.<name>.merge, no matter whether the remote branch exists
+    fn is_upstream_configured(&self) -> bool {
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return false,
+        };
+        if self.is_head_detached() {
+            return false;
+        }
+        let name = match self.get_head() {
+            Some(r) => match r.shorthand() {
+                Some(n) => n.to_string(),
+                None => return false,
+            },
+            None => return false,
+        };
+        let config = match repo.config() {
+            Ok(c) => c,
+            Err(e) => {
+                log!(self, "Can't read config of the repository: {:?}", e);
+                return false;
+            }
+        };
+        let remote = config.get_string(&format!("branch.{}.remote", name));
+        let merge = config.get_string(&format!("branch.{}.merge", name));
+        log!(self, "Upstream of branch {} in config: {:?} {:?}", name, remote, merge);
+        remote.is_ok() && merge.is_ok()
+    }
+
+    // the remote branch `git push` would update, the way git figures it out:
+    // branch.<name>.pushRemote, remote.pushDefault or branch.<name>.remote for the remote,
+    // push.default for the name of the branch
+    fn get_current_branch_push_oid(&self) -> Option<RefPair> {
+        let repo = self.repo.as_ref()?;
+        if self.is_head_detached() {
+            return None;
+        }
+        let name = self.get_head()?.shorthand()?.to_string();
+        let config = match repo.config() {
+            Ok(c) => c,
+            Err(e) => {
+                log!(self, "Can't read config of the repository: {:?}", e);
+                return None;
+            }
+        };
+        let get = |key: String| config.get_string(&key).ok();
+        let upstream_remote = get(format!("branch.{}.remote", name));
+        let remote_name = get(format!("branch.{}.pushRemote", name))
+            .or_else(|| get("remote.pushDefault".to_string()))
+            .or_else(|| upstream_remote.clone())?;
+        let merge = get(format!("branch.{}.merge", name))
+            .map(|m| m.trim_start_matches("refs/heads/").to_string());
+        let push_default = get("push.default".to_string()).unwrap_or_else(|| "simple".to_string());
+        let is_triangular = upstream_remote.as_ref() != Some(&remote_name);
+        let branch_name = match push_default.as_str() {
+            "nothing" => None,
+            "upstream" | "tracking" => merge,
+            // in a centralized workflow only to the branch of the same name it pulls from
+            "simple" if !is_triangular => merge.filter(|m| *m == name),
+            _ => Some(name),
+        };
+        let branch_name = match branch_name {
+            Some(b) => b,
+            None => {
+                log!(self, "Branch is not pushed anywhere, push.default = {}", push_default);
+                return None;
+            }
+        };
+        log!(self, "Branch is pushed to {}/{}", remote_name, branch_name);
+        let reference = format!("refs/remotes/{}/{}", remote_name, branch_name);
+        let oid = match repo.refname_to_id(&reference) {
+            Ok(o) => o,
+            Err(e) => {
+                log!(self, "Can't find {}: {:?}", reference, e);
+                return None;
+            }
+        };
+        Some(RefPair{ remote_name, branch_name, oid })
+    }
+
     fn get_current_branch_remote_oid(&self) -> Option<RefPair> {
         match self.get_head() {
             Some(r) => self.get_branch_remote(r),
@@ -336,20 +913,28 @@ impl Backend {
         }
     }
 
-    pub fn get_branch_ahead_behind(&self, remote_branch: Option<RemoteBranch>) -> Option<BranchAheadBehind> {
+    pub fn get_branch_ahead_behind(&self, remote_branch: Option<RemoteBranch>, target: CompareTarget,
+                                   walk: &WalkOptions) -> Option<BranchAheadBehind> {
         if let Some(ref demo) = self.demo {
-            return Some(self.get_demo_ahead_behind(demo, remote_branch));
+            return Some(self.get_demo_ahead_behind(demo, remote_branch, walk.limit));
         }
         let current_branch_name = self.get_current_branch_name();
         log!(self, "Current branch name = {:?}", current_branch_name);
         let mut ab = BranchAheadBehind::new(current_branch_name);
-        let ref_pair_option = self.get_remote_branch(remote_branch);
+        let follows_upstream = remote_branch.is_none() && target == CompareTarget::Upstream;
+        let ref_pair_option = self.get_remote_branch(remote_branch, target);
         let ref_pair = match ref_pair_option {
             Some(u) => {
                 u.clone()
             },
-            None => return Some(ab),
+            None => {
+                if follows_upstream && self.is_upstream_configured() {
+                    ab.upstream_state = UpstreamState::Gone;
+                }
+                return Some(ab);
+            },
         };
+        ab.upstream_state = UpstreamState::Tracking;
         ab.remote_branch_name = Some(ref_pair.branch_name.clone());
         ab.remote_name = Some(ref_pair.remote_name);
 
@@ -357,22 +942,165 @@ impl Backend {
             Some(r) => r,
             None => return None
         };
-        let repo = self.repo.as_ref()?;
-        let res = repo.graph_ahead_behind(oid, ref_pair.oid);
-        match res {
-            Ok((a, b)) => {
-                ab.ahead = a;
-                ab.behind = b;
+        let cached = if walk.cache { self.read_cached_counts(oid, ref_pair.oid, walk.limit) } else { None };
+        let counts = match cached {
+            Some(c) => Some(c),
+            None => match self.count_ahead_behind(oid, ref_pair.oid, walk.limit) {
+                Ok(c) => {
+                    if walk.cache {
+                        self.write_cached_counts(oid, ref_pair.oid, walk.limit, c);
+                    }
+                    Some(c)
+                },
+                Err(e) => {
+                    log!(self, "Can't get ahead & behind stats for branch {}: {:?}", ref_pair.branch_name, e);
+                    None
+                }
             },
-            Err(e) => {
-                log!(self, "Can't get ahead & behind stats for branch {}: {:?}", ref_pair.branch_name, e);
-            }
         };
+        if let Some((ahead, ahead_capped, behind, behind_capped)) = counts {
+            ab.ahead = ahead;
+            ab.ahead_capped = ahead_capped;
+            ab.behind = behind;
+            ab.behind_capped = behind_capped;
+        }
         Some(ab)
     }
 
-    fn get_demo_ahead_behind(&self, demo: &DemoData, remote_branch: Option<RemoteBranch>)
-            -> BranchAheadBehind {
+    fn count_ahead_behind(&self, local: Oid, remote: Oid, limit: Option<usize>) -> Result<Counts, Error> {
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return Ok((0, false, 0, false)),
+        };
+        let limit = match limit {
+            Some(l) => l,
+            None => {
+                let (ahead, behind) = repo.graph_ahead_behind(local, remote)?;
+                return Ok((ahead, false, behind, false));
+            }
+        };
+        // walk from both sides at once, the newest commits first, the way git looks for merge
+        // bases: a commit reached from one side only is ahead or behind; hiding a side in a
+        // revwalk would make libgit2 walk the whole difference before yielding anything
+        // the walk stops once both counts are past the limit or only common history is left
+        const LOCAL: u8 = 1;
+        const REMOTE: u8 = 2;
+        // the sides a commit is reached from and whether it was counted already
+        let mut sides: HashMap<Oid, (u8, bool)> = HashMap::new();
+        // of commits with the same date, the one found last goes first
+        let mut queue: BinaryHeap<(i64, usize, Oid)> = BinaryHeap::new();
+        let mut found: Vec<(Commit, u8)> = vec!((repo.find_commit(local)?, LOCAL), (repo.find_commit(remote)?, REMOTE));
+        let (mut ahead, mut behind, mut order) = (0, 0, 0);
+        loop {
+            for (commit, side) in found.drain(..) {
+                let entry = sides.entry(commit.id()).or_insert((0, false));
+                let (previous, counted) = *entry;
+                if previous | side == previous {
+                    continue;
+                }
+                *entry = (previous | side, false);
+                // a commit walked too early, before a newer one from the other side reached it
+                if counted {
+                    match previous {
+                        LOCAL => ahead -= 1,
+                        REMOTE => behind -= 1,
+                        _ => (),
+                    }
+                }
+                if counted || previous == 0 {
+                    order += 1;
+                    queue.push((commit.time().seconds(), order, commit.id()));
+                }
+            }
+            if ahead > limit && behind > limit {
+                break;
+            }
+            if queue.iter().all(|&(_, _, oid)| sides[&oid].0 == LOCAL | REMOTE) {
+                break;
+            }
+            let oid = match queue.pop() {
+                Some((_, _, oid)) => oid,
+                None => break,
+            };
+            let side = match sides.get_mut(&oid) {
+                Some(entry) => {
+                    entry.1 = true;
+                    entry.0
+                },
+                None => continue,
+            };
+            match side {
+                LOCAL => ahead += 1,
+                REMOTE => behind += 1,
+                _ => (),
+            }
+            for parent in repo.find_commit(oid)?.parents() {
+                found.push((parent, side));
+            }
+        }
+        Ok((ahead.min(limit), ahead > limit, behind.min(limit), behind > limit))
+    }
+
+    fn get_cache_path(&self) -> Option<PathBuf> {
+        Some(self.repo.as_ref()?.path().join(CACHE_FILE_NAME))
+    }
+
+    // a line of the cache: <LOCAL OID> <REMOTE OID> <LIMIT> <AHEAD>[+] <BEHIND>[+]
+    fn cache_key(local: Oid, remote: Oid, limit: Option<usize>) -> String {
+        let limit = match limit {
+            Some(l) => l.to_string(),
+            None => String::from("-"),
+        };
+        format!("{} {} {}", local, remote, limit)
+    }
+
+    fn read_cached_counts(&self, local: Oid, remote: Oid, limit: Option<usize>) -> Option<Counts> {
+        let content = fs::read_to_string(self.get_cache_path()?).ok()?;
+        let key = Backend::cache_key(local, remote, limit);
+        let parse = |s: &str| -> Option<(usize, bool)> {
+            let capped = s.ends_with('+');
+            s.trim_end_matches('+').parse::<usize>().ok().map(|n| (n, capped))
+        };
+        for line in content.lines() {
+            if let Some(rest) = line.strip_prefix(&key) {
+                let v: Vec<&str> = rest.split_whitespace().collect();
+                if v.len() == 2 {
+                    if let (Some((ahead, ahead_capped)), Some((behind, behind_capped))) = (parse(v[0]), parse(v[1])) {
+                        log!(self, "Found {} in the cache", line);
+                        return Some((ahead, ahead_capped, behind, behind_capped));
+                    }
+                }
+                log!(self, "Invalid line in the cache, counting again: {:?}", line);
+                return None;
+            }
+        }
+        None
+    }
+
+    // the cache keeps the latest entries only
+    fn write_cached_counts(&self, local: Oid, remote: Oid, limit: Option<usize>, counts: Counts) {
+        let path = match self.get_cache_path() {
+            Some(p) => p,
+            None => return,
+        };
+        let (ahead, ahead_capped, behind, behind_capped) = counts;
+        let mark = |capped: bool| if capped { "+" } else { "" };
+        let mut lines: Vec<String> = vec!(format!("{} {}{} {}{}", Backend::cache_key(local, remote, limit),
+                                                  ahead, mark(ahead_capped), behind, mark(behind_capped)));
+        if let Ok(content) = fs::read_to_string(&path) {
+            lines.extend(content.lines().take(CACHE_MAX_ENTRIES - 1).map(|l| l.to_string()));
+        }
+        // prompts redrawn at the same time must not read a half written file: a complete one
+        // replaces the cache at once
+        let tmp_path = path.with_file_name(format!("{}.{}.tmp", CACHE_FILE_NAME, process::id()));
+        if let Err(e) = fs::write(&tmp_path, lines.join("\n") + "\n").and_then(|_| fs::rename(&tmp_path, &path)) {
+            log!(self, "Can't write cache {:?}: {:?}", path, e);
+            let _ = fs::remove_file(&tmp_path);
+        }
+    }
+
+    fn get_demo_ahead_behind(&self, demo: &DemoData, remote_branch: Option<RemoteBranch>,
+                             limit: Option<usize>) -> BranchAheadBehind {
         let mut ab = BranchAheadBehind::new(Some(demo.branch_name.clone()));
         match remote_branch {
             // a remote branch requested in the config always exists in a demo
@@ -386,17 +1114,111 @@ impl Backend {
                     ab.remote_branch_name = Some(demo.branch_name.clone());
                 },
                 // the branch is not tracking anything
-                None => return ab,
+                None => {
+                    if demo.upstream_gone {
+                        ab.upstream_state = UpstreamState::Gone;
+                    }
+                    return ab;
+                },
             },
         };
-        ab.ahead = demo.ahead;
-        ab.behind = demo.behind;
+        ab.upstream_state = UpstreamState::Tracking;
+        let limit = limit.unwrap_or(usize::MAX);
+        ab.ahead = demo.ahead.min(limit);
+        ab.ahead_capped = demo.ahead > limit;
+        ab.behind = demo.behind.min(limit);
+        ab.behind_capped = demo.behind > limit;
         ab
     }
 
+    // remote branches matching a glob such as '*/main' or 'origin/release/*'
+    pub fn find_remote_branches(&self, glob: &str) -> Vec<RemoteBranch> {
+        if let Some(ref demo) = self.demo {
+            return self.find_demo_remote_branches(demo, glob);
+        }
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return vec!(),
+        };
+        let references = match repo.references_glob(&format!("refs/remotes/{}", glob)) {
+            Ok(r) => r,
+            Err(e) => {
+                log!(self, "Can't look up remote branches matching {}: {:?}", glob, e);
+                return vec!();
+            }
+        };
+        let mut found: Vec<RemoteBranch> = Vec::new();
+        for reference in references.flatten() {
+            // origin/HEAD
+            if reference.symbolic_target().is_some() {
+                continue;
+            }
+            let name = match reference.name() {
+                Some(n) => n.trim_start_matches("refs/remotes/"),
+                None => continue,
+            };
+            let v: Vec<&str> = name.splitn(2, '/').collect();
+            if v.len() == 2 {
+                found.push(RemoteBranch{ remote_branch: name.to_string(),
+                                         remote_name: v[0].to_string(),
+                                         remote_branch_name: v[1].to_string() });
+            }
+        }
+        log!(self, "Remote branches matching {}: {:?}", glob, found);
+        found
+    }
+
+    // a wildcard in the remote matches a couple of forks, the one in the branch our branch
+    fn find_demo_remote_branches(&self, demo: &DemoData, glob: &str) -> Vec<RemoteBranch> {
+        let v: Vec<&str> = glob.splitn(2, '/').collect();
+        if v.len() != 2 {
+            return vec!();
+        }
+        let is_glob = |s: &str| s.contains(['*', '?', '[']);
+        let remotes = if is_glob(v[0]) { vec!("origin", "upstream") } else { vec!(v[0]) };
+        let branch_name = if is_glob(v[1]) { demo.branch_name.clone() } else { v[1].to_string() };
+        remotes.iter().map(|r| RemoteBranch{
+            remote_branch: format!("{}/{}", r, branch_name),
+            remote_name: r.to_string(),
+            remote_branch_name: branch_name.clone(),
+        }).collect()
+    }
+
+    pub fn remote_branch_exists(&self, remote_branch: &RemoteBranch) -> bool {
+        if self.demo.is_some() {
+            return true;
+        }
+        match self.repo {
+            Some(ref repo) => repo.find_branch(&remote_branch.remote_branch, BranchType::Remote).is_ok(),
+            None => false,
+        }
+    }
+
+    // name of the default branch of a remote: where refs/remotes/<remote>/HEAD points to,
+    // the first of fallback_names which exists on the remote when HEAD is not set
+    pub fn get_remote_default_branch(&self, remote: &str, fallback_names: &[String]) -> Option<String> {
+        if self.demo.is_some() {
+            return fallback_names.first().cloned();
+        }
+        let repo = self.repo.as_ref()?;
+        let head_name = format!("refs/remotes/{}/HEAD", remote);
+        if let Ok(head) = repo.find_reference(&head_name) {
+            if let Some(target) = head.symbolic_target() {
+                let prefix = format!("refs/remotes/{}/", remote);
+                log!(self, "{} points to {}", head_name, target);
+                return Some(target.trim_start_matches(prefix.as_str()).to_string());
+            }
+        }
+        log!(self, "{} is not set, trying {:?}", head_name, fallback_names);
+        fallback_names.iter().find(|name| {
+            repo.find_branch(&format!("{}/{}", remote, name), BranchType::Remote).is_ok()
+        }).cloned()
+    }
+
     // find remote branch if branch_name is specified
-    // if not, get remote tracking branch for current branch
-    fn get_remote_branch(&self, remote_branch: Option<RemoteBranch>) -> Option<RefPair> {
+    // if not, get remote tracking branch for current branch, or the one it's pushed to
+    fn get_remote_branch(&self, remote_branch: Option<RemoteBranch>, target: CompareTarget)
+            -> Option<RefPair> {
         let repo = self.repo.as_ref()?;
         match remote_branch {
             Some(b) => {
@@ -417,17 +1239,461 @@ impl Backend {
                     }
                 }
             },
+            None => match target {
+                CompareTarget::Upstream => self.get_current_branch_remote_oid(),
+                CompareTarget::Push => self.get_current_branch_push_oid(),
+            }
+        }
+    }
+
+    pub fn get_head_commit(&self) -> Option<CommitInfo> {
+        if let Some(ref demo) = self.demo {
+            return demo.head_commit.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let oid = self.get_current_branch_oid()?;
+        let commit = match repo.find_commit(oid) {
+            Ok(c) => c,
+            Err(e) => {
+                log!(self, "Can't find commit of HEAD: {:?}", e);
+                return None;
+            }
+        };
+        let author = commit.author().name().unwrap_or_default().to_string();
+        Some(CommitInfo{
+            subject: commit.summary().unwrap_or_default().to_string(),
+            author,
+            time: commit.time().seconds(),
+            is_merge: commit.parent_count() > 1,
+        })
+    }
+
+    // the merge base of HEAD and base, which is anything `git rev-parse` understands
+    pub fn get_fork_point(&self, base: &str) -> Option<ForkPoint> {
+        if let Some(ref demo) = self.demo {
+            return demo.fork_point.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let base_oid = match repo.revparse_single(base).and_then(|o| o.peel_to_commit()) {
+            Ok(c) => c.id(),
+            Err(e) => {
+                log!(self, "Can't find base {}: {:?}", base, e);
+                return None;
+            }
+        };
+        let head_oid = self.get_current_branch_oid()?;
+        let merge_base = match repo.merge_base(head_oid, base_oid) {
+            Ok(o) => o,
+            Err(e) => {
+                log!(self, "No merge base of HEAD and {}: {:?}", base, e);
+                return None;
+            }
+        };
+        let time = match repo.find_commit(merge_base) {
+            Ok(c) => c.time().seconds(),
+            Err(e) => {
+                log!(self, "Can't find merge base {}: {:?}", merge_base, e);
+                return None;
+            }
+        };
+        let (ahead, behind) = match repo.graph_ahead_behind(head_oid, base_oid) {
+            Ok(a_b) => a_b,
+            Err(e) => {
+                log!(self, "Can't get ahead & behind stats for {}: {:?}", base, e);
+                return None;
+            }
+        };
+        log!(self, "HEAD forked off {} at {}: {} ahead, {} behind", base, merge_base, ahead, behind);
+        Some(ForkPoint{ ahead, behind, time })
+    }
+
+    // None when there are no submodules; checking the content of submodules for changes is
+    // skipped with ignore_dirty, since it means getting status of every one of them
+    pub fn get_submodule_summary(&self, ignore_dirty: bool) -> Option<SubmoduleSummary> {
+        if let Some(ref demo) = self.demo {
+            return demo.submodules.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let submodules = match repo.submodules() {
+            Ok(s) => s,
+            Err(e) => {
+                log!(self, "Can't list submodules: {:?}", e);
+                return None;
+            }
+        };
+        if submodules.is_empty() {
+            return None;
+        }
+        let ignore = if ignore_dirty { SubmoduleIgnore::Dirty } else { SubmoduleIgnore::None };
+        let dirty = SubmoduleStatus::WD_INDEX_MODIFIED | SubmoduleStatus::WD_WD_MODIFIED |
+            SubmoduleStatus::WD_UNTRACKED;
+        let mut summary = SubmoduleSummary{ modified: 0, uninitialized: 0, out_of_sync: 0 };
+        for submodule in &submodules {
+            let name = match submodule.name() {
+                Some(n) => n,
+                None => continue,
+            };
+            let status = match repo.submodule_status(name, ignore) {
+                Ok(s) => s,
+                Err(e) => {
+                    log!(self, "Can't get status of submodule {}: {:?}", name, e);
+                    continue;
+                }
+            };
+            log!(self, "Submodule {} status: {:?}", name, status);
+            if status.contains(SubmoduleStatus::WD_UNINITIALIZED) {
+                summary.uninitialized += 1;
+                continue;
+            }
+            if status.contains(SubmoduleStatus::WD_MODIFIED) {
+                summary.out_of_sync += 1;
+            }
+            if status.intersects(dirty) {
+                summary.modified += 1;
+            }
+        }
+        Some(summary)
+    }
+
+    // locked and prunable are counted among the other linked work trees
+    pub fn get_worktree_info(&self) -> Option<WorktreeInfo> {
+        if let Some(ref demo) = self.demo {
+            return demo.worktree.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let name = if repo.is_worktree() {
+            match Worktree::open_from_repository(repo) {
+                Ok(w) => w.name().map(|n| n.to_string()),
+                Err(e) => {
+                    log!(self, "Can't open the current work tree: {:?}", e);
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+        let names = match repo.worktrees() {
+            Ok(n) => n,
+            Err(e) => {
+                log!(self, "Can't list work trees: {:?}", e);
+                return None;
+            }
+        };
+        // the main work tree is one of the others when we're in a linked one
+        let mut info = WorktreeInfo{ others: if name.is_some() { 1 } else { 0 },
+                                     name, locked: 0, prunable: 0 };
+        for other in names.iter().flatten() {
+            if info.name.as_deref() == Some(other) {
+                continue;
+            }
+            info.others += 1;
+            let worktree = match repo.find_worktree(other) {
+                Ok(w) => w,
+                Err(e) => {
+                    log!(self, "Can't open work tree {}: {:?}", other, e);
+                    continue;
+                }
+            };
+            if let Ok(WorktreeLockStatus::Locked(_)) = worktree.is_locked() {
+                info.locked += 1;
+            }
+            if worktree.is_prunable(None).unwrap_or(false) {
+                info.prunable += 1;
+            }
+        }
+        log!(self, "Work trees: {:?}", info);
+        Some(info)
+    }
+
+    pub fn get_repo_flags(&self) -> RepoFlags {
+        if let Some(ref demo) = self.demo {
+            return demo.flags.clone();
+        }
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return RepoFlags::default(),
+        };
+        let mut flags = RepoFlags{ shallow: repo.is_shallow(), bare: repo.is_bare(), ..RepoFlags::default() };
+        match repo.config() {
+            Ok(config) => {
+                flags.sparse = config.get_bool("core.sparseCheckout").unwrap_or(false);
+                flags.partial = config.get_string("extensions.partialClone").is_ok();
+                if let Ok(mut entries) = config.entries(Some("remote\\..*\\.promisor")) {
+                    while let Some(Ok(entry)) = entries.next() {
+                        let is_promisor = match entry.value() {
+                            Some(v) => !["false", "no", "off", "0"].contains(&v.to_lowercase().as_str()),
+                            None => true,
+                        };
+                        flags.partial = flags.partial || is_promisor;
+                    }
+                }
+            },
+            Err(e) => log!(self, "Can't read config of the repository: {:?}", e),
+        };
+        if !flags.bare {
+            let current_dir = env::current_dir().and_then(|d| d.canonicalize());
+            let git_dir = repo.path().canonicalize();
+            if let (Ok(c), Ok(g)) = (current_dir, git_dir) {
+                flags.in_git_dir = c.starts_with(g);
+            }
+        }
+        log!(self, "Repository flags: {:?}", flags);
+        flags
+    }
+
+    // (name, URL) of the remote; when no name is given, of the remote the current branch
+    // tracks, `origin` if it doesn't track any
+    pub fn get_remote_url(&self, name: Option<&str>) -> Option<(String, String)> {
+        if let Some(ref demo) = self.demo {
+            let remote_name = demo.remote_name.clone().unwrap_or_else(|| String::from("origin"));
+            return demo.remote_url.clone().map(|u| (remote_name, u));
+        }
+        let repo = self.repo.as_ref()?;
+        let remote_name = match name {
+            Some(n) => n.to_string(),
+            None => match self.get_current_branch_remote_oid() {
+                Some(ref_pair) => ref_pair.remote_name,
+                None => String::from("origin"),
+            },
+        };
+        let remote = match repo.find_remote(&remote_name) {
+            Ok(r) => r,
+            Err(e) => {
+                log!(self, "Can't find remote {}: {:?}", remote_name, e);
+                return None;
+            }
+        };
+        let url = remote.url()?.to_string();
+        log!(self, "URL of remote {} is {}", remote_name, url);
+        Some((remote_name, url))
+    }
+
+    // (remote, name of the branch on the remote) of the branch the current branch tracks
+    pub fn get_upstream_branch(&self) -> Option<(String, String)> {
+        if let Some(ref demo) = self.demo {
+            return demo.remote_name.clone().map(|r| (r, demo.branch_name.clone()));
+        }
+        let ref_pair = self.get_current_branch_remote_oid()?;
+        // the shorthand is <REMOTE>/<BRANCH>
+        let prefix = format!("{}/", ref_pair.remote_name);
+        let branch = match ref_pair.branch_name.strip_prefix(&prefix) {
+            Some(b) => b.to_string(),
+            None => ref_pair.branch_name.clone(),
+        };
+        Some((ref_pair.remote_name, branch))
+    }
+
+    // value of a key in the effective config of the repository: repository, global and system
+    pub fn get_config_value(&self, key: &str) -> Option<String> {
+        if let Some(ref demo) = self.demo {
+            return demo.config.get(key).cloned();
+        }
+        let repo = self.repo.as_ref()?;
+        let config = match repo.config() {
+            Ok(c) => c,
+            Err(e) => {
+                log!(self, "Can't read config of the repository: {:?}", e);
+                return None;
+            }
+        };
+        match config.get_string(key) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                log!(self, "Can't get {} from config: {:?}", key, e);
+                None
+            }
+        }
+    }
+
+    // conflicting files in the index, sorted by path
+    pub fn get_conflicts(&self) -> Vec<Conflict> {
+        if let Some(ref demo) = self.demo {
+            return demo.conflicts.clone();
+        }
+        let repo = match self.repo {
+            Some(ref r) => r,
+            None => return vec!(),
+        };
+        let index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => {
+                log!(self, "Can't read the index: {:?}", e);
+                return vec!();
+            }
+        };
+        let index_conflicts = match index.conflicts() {
+            Ok(c) => c,
+            Err(e) => {
+                log!(self, "Can't read conflicts from the index: {:?}", e);
+                return vec!();
+            }
+        };
+        let mut conflicts: Vec<Conflict> = Vec::new();
+        for c in index_conflicts.flatten() {
+            let entry = match c.our.as_ref().or(c.their.as_ref()).or(c.ancestor.as_ref()) {
+                Some(e) => e,
+                None => continue,
+            };
+            conflicts.push(Conflict{
+                path: String::from_utf8_lossy(&entry.path).into_owned(),
+                kind: ConflictKind::from_stages(c.ancestor.is_some(), c.our.is_some(), c.their.is_some()),
+            });
+        }
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        log!(self, "Conflicts: {:?}", conflicts);
+        conflicts
+    }
+
+    // None when no file in the index has LFS attributes: filter=lfs or lockable, set in any
+    // .gitattributes, in .git/info/attributes or in core.attributesFile
+    pub fn get_lfs_summary(&self) -> Option<LfsSummary> {
+        if let Some(ref demo) = self.demo {
+            return demo.lfs.clone();
+        }
+        let repo = self.repo.as_ref()?;
+        let workdir = repo.workdir()?;
+        let index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => {
+                log!(self, "Can't read the index: {:?}", e);
+                return None;
+            }
+        };
+        // looking up attributes of every file is not cheap, most repositories don't use LFS
+        if !self.mentions_lfs_attributes(repo, &index) {
+            return None;
+        }
+        let locks = self.get_lfs_locks(repo);
+        let mut uses_lfs = false;
+        let mut summary = LfsSummary{ pointers: 0, locked: 0 };
+        for entry in index.iter() {
+            let path_str = match str::from_utf8(&entry.path) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            if locks.contains(path_str) {
+                summary.locked += 1;
+            }
+            let path = Path::new(path_str);
+            let flags = AttrCheckFlags::FILE_THEN_INDEX;
+            let is_lfs = repo.get_attr(path, "filter", flags).unwrap_or(None) == Some("lfs");
+            if !is_lfs {
+                if !uses_lfs {
+                    let lockable = repo.get_attr(path, "lockable", flags).unwrap_or(None);
+                    uses_lfs = AttrValue::from_string(lockable) == AttrValue::True;
+                }
+                continue;
+            }
+            uses_lfs = true;
+            // a file larger than a pointer in the index is not read
+            if u64::from(entry.file_size) <= LFS_POINTER_MAX_SIZE && Backend::is_lfs_pointer(&workdir.join(path)) {
+                summary.pointers += 1;
+            }
+        }
+        if !uses_lfs {
+            return None;
+        }
+        log!(self, "LFS: {:?}", summary);
+        Some(summary)
+    }
+
+    // whether any of the attribute files sets filter=lfs or lockable
+    fn mentions_lfs_attributes(&self, repo: &Repository, index: &Index) -> bool {
+        let mut paths: Vec<PathBuf> = vec!(repo.path().join("info").join("attributes"));
+        if let Ok(p) = repo.config().and_then(|c| c.get_path("core.attributesFile")) {
+            paths.push(p);
+        }
+        if let Some(workdir) = repo.workdir() {
+            for entry in index.iter() {
+                if entry.path == b".gitattributes" || entry.path.ends_with(b"/.gitattributes") {
+                    if let Ok(p) = str::from_utf8(&entry.path) {
+                        paths.push(workdir.join(p));
+                    }
+                }
+            }
+        }
+        let found = paths.iter().any(|p| match fs::read_to_string(p) {
+            Ok(content) => content.contains("filter=lfs") || content.contains("lockable"),
+            Err(_) => false,
+        });
+        if !found {
+            log!(self, "No LFS attributes in {:?}", paths);
+        }
+        found
+    }
+
+    // paths of the files locked by you, as git-lfs recorded them locally when locking them
+    fn get_lfs_locks(&self, repo: &Repository) -> HashSet<String> {
+        let path = repo.path().join(LFS_LOCK_CACHE);
+        let data = match fs::read(&path) {
+            Ok(d) => d,
+            // nothing was locked yet
+            Err(_) => return HashSet::new(),
+        };
+        // the locks are stored under their paths and under their ids
+        match gob_map_keys(&data) {
+            Some(keys) => keys.into_iter().collect(),
             None => {
-                self.get_current_branch_remote_oid()
+                log!(self, "Can't read the LFS lock cache {:?}", path);
+                HashSet::new()
             }
         }
     }
 
+    // a pointer file is small and starts with the version of the specification
+    fn is_lfs_pointer(path: &Path) -> bool {
+        match fs::metadata(path) {
+            Ok(m) if m.len() <= LFS_POINTER_MAX_SIZE => (),
+            _ => return false,
+        }
+        match fs::read(path) {
+            Ok(content) => content.starts_with(LFS_POINTER_PREFIX.as_bytes()),
+            Err(_) => false,
+        }
+    }
+
+    // name of the directory of the work tree, of the git directory without `.git` in a bare
+    // repository
+    pub fn get_repo_name(&self) -> Option<String> {
+        if let Some(ref demo) = self.demo {
+            return Some(demo.repo_name.clone());
+        }
+        let repo = self.repo.as_ref()?;
+        let dir = repo.workdir().unwrap_or_else(|| repo.path());
+        let name = dir.file_name()?.to_str()?;
+        Some(name.strip_suffix(".git").unwrap_or(name).to_string())
+    }
+
+    // components of the path from the root of the work tree to the current directory; None
+    // outside of the work tree
+    pub fn get_relative_dir(&self) -> Option<Vec<String>> {
+        if let Some(ref demo) = self.demo {
+            return Some(demo.relative_dir.split('/').filter(|c| !c.is_empty())
+                        .map(|c| c.to_string()).collect());
+        }
+        let repo = self.repo.as_ref()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let current_dir = env::current_dir().and_then(|d| d.canonicalize()).ok()?;
+        let relative = match current_dir.strip_prefix(&workdir) {
+            Ok(r) => r,
+            Err(_) => {
+                log!(self, "{:?} is not inside of the work tree {:?}", current_dir, workdir);
+                return None;
+            }
+        };
+        // the git directory is not a part of the work tree
+        if current_dir.starts_with(repo.path().canonicalize().ok()?) {
+            return None;
+        }
+        Some(relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect())
+    }
+
     pub fn get_status(&self) -> Option<Statuses<'_>> {
         let repo = self.repo.as_ref()?;
         let mut so = StatusOptions::new();
         let mut opts = so.show(StatusShow::IndexAndWorkdir);
         opts.include_untracked(true);
+        opts.exclude_submodules(self.exclude_submodules);
         match repo.statuses(Some(&mut opts)) {
             Ok(s) => Some(s),
             Err(e) => {
@@ -501,25 +1767,37 @@ impl Backend {
         Some(d)
     }
 
-    pub fn get_stash_count(&mut self) -> u16 {
+    // items in the stash, the newest first
+    pub fn get_stashes(&mut self) -> Vec<StashInfo> {
         if let Some(ref demo) = self.demo {
-            return demo.stash_count;
+            return demo.stashes.clone();
         }
         let repo = match self.repo {
             Some(ref mut r) => r,
-            None => return 0,
+            None => return vec!(),
         };
-        let mut count: u16 = 0;
+        let mut entries: Vec<(String, Oid)> = Vec::new();
         let r = repo.stash_foreach(
-            |_u: usize, _s: &str, _o: &Oid| {
-                count += 1;
+            |_u: usize, s: &str, o: &Oid| {
+                entries.push((s.to_string(), *o));
                 true
             }
         );
-        match r {
-            Ok(_) => log!(self, "Stash contains {} items", count),
-            Err(e) => log!(self, "There was an error while checking stash: {:?}", e),
+        if let Err(e) = r {
+            log!(self, "There was an error while checking stash: {:?}", e);
+        }
+        let mut stashes: Vec<StashInfo> = Vec::new();
+        for (message, oid) in entries {
+            let time = match repo.find_commit(oid) {
+                Ok(c) => c.time().seconds(),
+                Err(e) => {
+                    log!(self, "Can't find stash commit {}: {:?}", oid, e);
+                    continue;
+                }
+            };
+            stashes.push(StashInfo{ branch: StashInfo::parse_branch(&message), time });
         }
-        count
+        log!(self, "Stash contains {} items", stashes.len());
+        stashes
     }
 }
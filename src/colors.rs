@@ -117,7 +117,7 @@ fn style_snippet(shell: Shell, zsh_on: Option<&str>, zsh_off: Option<&str>, code
 }
 
 // resolve a zsh color spec (a name or a number) into a 256-color code
-fn color_code(spec: &str) -> Option<u8> {
+pub fn color_code(spec: &str) -> Option<u8> {
     let s = spec.trim();
     if let Ok(n) = s.parse::<u16>() {
         if n <= 255 {
@@ -244,6 +244,18 @@ fn strip_ansi(s: &str) -> String {
             continue;
         }
         i += 1;
+        if i < chars.len() && chars[i] == ']' {
+            // operating system command, e.g. a hyperlink, terminated by BEL or ESC \
+            i += 1;
+            while i < chars.len() && chars[i] != '\x07' && chars[i] != '\x1b' {
+                i += 1;
+            }
+            if i < chars.len() && chars[i] == '\x1b' {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
         if i < chars.len() && chars[i] == '[' {
             i += 1;
             // parameter and intermediate bytes, terminated by a final byte
@@ -258,6 +270,34 @@ fn strip_ansi(s: &str) -> String {
     out
 }
 
+// text which the terminal turns into a link to url (OSC 8); the escape sequences are marked
+// as taking no space on screen in the syntax of the shell
+pub fn hyperlink(shell: Shell, url: &str, text: &str) -> String {
+    match shell {
+        // '%' of an encoded URL would be a prompt escape in zsh
+        Shell::Zsh => format!("%{{\x1b]8;;{}\x1b\\%}}{}%{{\x1b]8;;\x1b\\%}}", url.replace('%', "%%"), text),
+        Shell::Bash => format!("\\[\\e]8;;{}\\e\\\\\\]{}\\[\\e]8;;\\e\\\\\\]", url, text),
+    }
+}
+
+// what a color is set for: the text itself or the cells behind it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    Foreground,
+    Background,
+}
+
+// the escape which sets a layer to a 256-color code, None sets it back to the default color
+pub fn layer_color(shell: Shell, layer: Layer, code: Option<u8>) -> String {
+    let is_fg = layer == Layer::Foreground;
+    match (shell, code) {
+        (Shell::Zsh, Some(c)) => format!("%{{%{}{{{}}}%}}", if is_fg { 'F' } else { 'K' }, c),
+        (Shell::Zsh, None) => format!("%{{%{}%}}", if is_fg { 'f' } else { 'k' }),
+        (Shell::Bash, Some(c)) => format!("\\[\\e[{};5;{}m\\]", if is_fg { 38 } else { 48 }, c),
+        (Shell::Bash, None) => format!("\\[\\e[{}m\\]", if is_fg { 39 } else { 49 }),
+    }
+}
+
 // turn a string formatted for a shell prompt into a string a terminal renders
 // the same way; with colors disabled all the formatting is dropped instead
 pub fn render(s: &str, shell: Shell, colors: bool) -> String {
@@ -481,6 +521,30 @@ mod tests {
         assert_eq!(render("\\[\\e[38;5;4m\\]master\\[\\e[0m\\]", Shell::Bash, false), "master");
     }
 
+    #[test]
+    fn test_hyperlink() {
+        for shell in [Shell::Bash, Shell::Zsh].iter() {
+            let link = hyperlink(*shell, "https://example.com/", "master");
+            assert_eq!(render(&link, *shell, true), "\x1b]8;;https://example.com/\x1b\\master\x1b]8;;\x1b\\");
+            assert_eq!(render(&link, *shell, false), "master");
+        }
+        let encoded = hyperlink(Shell::Zsh, "https://example.com/tree/100%25done", "master");
+        assert_eq!(render(&encoded, Shell::Zsh, true), "\x1b]8;;https://example.com/tree/100%25done\x1b\\master\x1b]8;;\x1b\\");
+    }
+
+    #[test]
+    fn test_layer_color() {
+        assert_eq!(layer_color(Shell::Zsh, Layer::Background, Some(4)), "%{%K{4}%}");
+        assert_eq!(layer_color(Shell::Zsh, Layer::Foreground, None), "%{%f%}");
+        assert_eq!(layer_color(Shell::Bash, Layer::Foreground, Some(15)), "\\[\\e[38;5;15m\\]");
+        assert_eq!(layer_color(Shell::Bash, Layer::Background, None), "\\[\\e[49m\\]");
+        for shell in [Shell::Bash, Shell::Zsh].iter() {
+            let s = layer_color(*shell, Layer::Background, Some(4)) + "x"
+                + &layer_color(*shell, Layer::Background, None);
+            assert_eq!(render(&s, *shell, true), "\x1b[48;5;4mx\x1b[49m");
+        }
+    }
+
     #[test]
     fn test_color_code() {
         assert_eq!(color_code("blue"), Some(4));
@@ -7,7 +7,9 @@ use std::io;
 use std::io::{Write,Read};
 use std::path::{Path,PathBuf};
 
-use constants::{get_default_config_path, CURRENT_CONFIG_VERSION};
+use colors::{Layer,Shell,color_code,layer_color};
+use constants::{get_default_config_path, CURRENT_CONFIG_VERSION, POWERLINE_SEPARATOR,
+                POWERLINE_ASCII_SEPARATOR};
 use models::{DisplayMaster,SimpleValue,format_value};
 
 use yaml_rust::{YamlLoader, Yaml};
@@ -58,12 +60,66 @@ values:
       # example: 'upstream/master'
       # if omitted look for remotely tracked branch usually set up with:
       #   git branch --set-upstream-to
+      # <REMOTE> stands for the first of 'remotes' which has the branch and HEAD
+      # for the default branch of the remote, 'default_branch' is the same as
+      # '<REMOTE>/HEAD'
+      # it can also be a list of branches, or a glob matching remote branches such as
+      # '*/main' or 'origin/release/*': 'values' are displayed for each of them
       # remote_branch: ''
+      # displayed between the values of different remote branches (optional,
+      # defaults to ''), type string
+      # group_separator: ' '
+      # the maximal number of remote branches displayed (optional), type int
+      # max_items: 3
+      # remotes tried for <REMOTE> in this order (optional, defaults to
+      # ['upstream', 'origin']), type list of strings
+      # remotes: ['upstream', 'origin']
+      # names of the default branch tried when HEAD of a remote is not set, which
+      # is fixed with 'git remote set-head <REMOTE> --auto' (optional, defaults to
+      # ['main', 'master']), type list of strings
+      # default_branch_names: ['main', 'master']
+      # which remote branch is followed when 'remote_branch' is omitted (optional,
+      # defaults to 'upstream'), type string
+      #  * upstream -- the one the branch pulls from
+      #  * push -- the one 'git push' updates, which differs in a triangular workflow:
+      #            branch.<name>.pushRemote, remote.pushDefault and push.default
+      #            are respected
+      # target: 'upstream'
+      # stop counting commits ahead and behind after this many (optional), type int
+      # the count is displayed as e.g. '999+' then; keeps the prompt fast when the
+      # branches diverged a lot
+      # walk_limit: 999
+      # remember the counts in the git directory, so they're not counted again
+      # until one of the branches moves (optional, defaults to false), type bool
+      # cache: false
       # display the remote even if there is no difference with current branch (required), type bool
       display_if_uptodate: true
       # what <REMOTE_FIRST_LETTER> renders as when there is no remote
       # counterpart (optional, defaults to '_'), type string
       # no_remote_placeholder: '_'
+      # how the checked out commit is named when HEAD is detached (optional,
+      # defaults to 'hash'), type string or list of strings
+      # when it's a list, the first name which can be figured out is used:
+      #  * tag -- a tag pointing at the commit
+      #  * branch -- the nearest branch containing the commit, e.g. 'master~3'
+      #  * hash -- abbreviated hash of the commit, used when nothing else is found
+      # detached_head: ['tag', 'branch', 'hash']
+      # minimal length of the abbreviated hash (optional, defaults to 7), type int
+      # the hash gets longer when it wouldn't be unique in the repository
+      # hash_length: 7
+      # what <DETACHED> renders as when HEAD is detached (optional, defaults
+      # to ''), type string
+      # detached_marker: '➦'
+      # formatting used instead of 'values' when the branch doesn't track a remote
+      # branch or when 'remote_branch' doesn't exist (optional), type dict
+      # no_upstream_format:
+      #   pre_format: '<LOCAL_BRANCH>'
+      #   post_format: ' (local)'
+      # formatting used instead of 'values' when the tracked remote branch was
+      # deleted, e.g. after its pull request was merged (optional), type dict
+      # gone_format:
+      #   pre_format: '<LOCAL_BRANCH>'
+      #   post_format: ' (gone)'
       pre_format: ''
       post_format: ''
       # values which can be displayed as part of 'remote_difference'
@@ -77,6 +133,25 @@ values:
           #  * <REMOTE_FIRST_LETTER> will be replaced with the first letter of
           #    a remote name, or with 'no_remote_placeholder' if the branch has
           #    no remote counterpart
+          #  * <DETACHED> will be replaced with 'detached_marker' when HEAD is
+          #    detached
+          #  * <UPSTREAM_STATE> will be replaced with 'gone' when the tracked
+          #    remote branch was deleted, with 'none' when there is no remote
+          #    branch and with nothing otherwise
+          # <LOCAL_BRANCH> and <REMOTE_BRANCH> can be rewritten (optional), type list
+          # every rule is a regular expression and its replacement, applied in
+          # order; $1 in the replacement stands for the first group
+          # rewrite:
+          #   - pattern: '^feature/'
+          #     replacement: 'f/'
+          #   - pattern: '^.*(JIRA-[0-9]+).*$'
+          #     replacement: '$1'
+          # and shortened to a maximal number of characters (optional), type int
+          # max_length: 20
+          # what replaces the part which is cut off (optional, defaults to '…'), type string
+          # ellipsis: '…'
+          # which side is cut off, 'right' or 'left' (optional, defaults to 'right'), type string
+          # truncate: 'right'
           pre_format: '<LOCAL_BRANCH>'
           post_format: ''
           # the number of files present locally which are missing in remote repo
@@ -99,8 +174,10 @@ values:
       display: surrounded
       pre_format: '│'
       post_format: ''
+      # the default branch of remote 'upstream'
     - type: remote_difference
-      remote_branch: 'upstream/master'
+      remote_branch: 'default_branch'
+      remotes: ['upstream']
       display_if_uptodate: false
       pre_format: ''
       post_format: ''
@@ -131,9 +208,15 @@ values:
       pre_format: '▶'
       post_format: ''
       # during merge, rebase, or others, the numbers files which conflict
+      # <FILES> lists names of the first max_files of them (3 by default);
+      # <BOTH_MODIFIED>, <ADDED_BY_BOTH>, <DELETED_BY_US> and <DELETED_BY_THEM>
+      # count them by the kind of the conflict
     - type: conflicts
       pre_format: '✖'
       post_format: ''
+      # the number of items in the stash; <AGE> is how old the newest one is and
+      # <ON_BRANCH> is replaced with on_branch_marker when any of them was created
+      # on the current branch; current_branch_only: true counts only those
     - type: stash
       pre_format: '☐'
       post_format: ''
@@ -176,11 +259,93 @@ impl Separator {
 
 
 
+// colors of a segment, as 256-color codes
+#[derive(Debug, Clone, Copy)]
+struct SegmentColors {
+    background: u8,
+    // None keeps the default color of the text
+    foreground: Option<u8>,
+}
+
+impl SegmentColors {
+    fn new(value_yaml: &Yaml) -> SegmentColors {
+        let background = match SegmentColors::parse_color(value_yaml, "background") {
+            Some(c) => c,
+            None => panic!("segment needs to have specified 'background' attribute"),
+        };
+        SegmentColors{
+            background,
+            foreground: SegmentColors::parse_color(value_yaml, "foreground"),
+        }
+    }
+
+    // a name of a color or a number of the 256-color palette
+    fn parse_color(value_yaml: &Yaml, key: &str) -> Option<u8> {
+        let spec = match value_yaml[key] {
+            Yaml::Integer(n) => n.to_string(),
+            Yaml::String(ref s) => s.clone(),
+            Yaml::BadValue | Yaml::Null => return None,
+            ref y => panic!("'{}' needs to be a color, not {:?}", key, y),
+        };
+        match color_code(&spec) {
+            Some(c) => Some(c),
+            None => panic!("'{}' is not a color: {}", key, spec),
+        }
+    }
+}
+
+// the glyph between two segments is drawn in the background color of the segment on its left
+// over the background of the segment on its right, so the segments seem to flow into each other
+struct Powerline {
+    separator: String,
+    shell: Shell,
+}
+
+impl Powerline {
+    fn new(powerline_yaml: &Yaml, shell: Shell) -> Powerline {
+        // for terminals without a Powerline font
+        let (key, default) = if powerline_yaml["ascii"].as_bool().unwrap_or(false) {
+            ("ascii_separator", POWERLINE_ASCII_SEPARATOR)
+        } else {
+            ("separator", POWERLINE_SEPARATOR)
+        };
+        let separator = match powerline_yaml[key].as_str() {
+            Some(s) => s.to_string(),
+            None => default.to_string(),
+        };
+        Powerline{ separator, shell }
+    }
+
+    // the colors of a segment, preceded by the glyph when there is a segment right before it
+    fn start(&self, previous: Option<&SegmentColors>, colors: &SegmentColors) -> String {
+        let mut out = String::new();
+        if let Some(p) = previous {
+            out += &layer_color(self.shell, Layer::Foreground, Some(p.background));
+            out += &layer_color(self.shell, Layer::Background, Some(colors.background));
+            out += &self.separator;
+        } else {
+            out += &layer_color(self.shell, Layer::Background, Some(colors.background));
+        }
+        out + &layer_color(self.shell, Layer::Foreground, colors.foreground)
+    }
+
+    // the glyph after the last segment in a row, over the default background
+    fn end(&self, colors: &SegmentColors) -> String {
+        layer_color(self.shell, Layer::Foreground, Some(colors.background))
+            + &layer_color(self.shell, Layer::Background, None)
+            + &self.separator
+            + &layer_color(self.shell, Layer::Foreground, None)
+    }
+}
+
+
 // a value from config together with the string it renders into
 enum Item {
     // None means the value is not displayed at all
     Value(Option<String>),
     Separator { text: String, always: bool },
+    // values grouped on a background of their own, powerline style
+    Segment { text: Option<String>, colors: SegmentColors },
 }
 
 // a separator with 'display: surrounded' is displayed only when there is a value displayed on
@@ -200,24 +365,42 @@ fn scan_values(items: &[Item]) -> (bool, bool) {
     let mut has_value = false;
     let mut is_set = false;
     for item in items {
-        if let Item::Value(ref value) = *item {
-            has_value = true;
-            if value.is_some() {
-                is_set = true;
-            }
+        match *item {
+            Item::Value(ref value) | Item::Segment { text: ref value, .. } => {
+                has_value = true;
+                if value.is_some() {
+                    is_set = true;
+                }
+            },
+            Item::Separator { .. } => (),
         }
     }
     (has_value, is_set)
 }
 
 
+// `ignore_dirty: true` of the submodules value keeps the status of the whole prompt from
+// scanning the work trees of submodules
+fn ignores_dirty_submodules(values_yaml: &Yaml) -> bool {
+    let values = match values_yaml.as_vec() {
+        Some(v) => v,
+        None => return false,
+    };
+    values.iter().any(|v| {
+        let is_ignoring = v["type"].as_str() == Some("submodules") && v["ignore_dirty"].as_bool() == Some(true);
+        // values of a segment
+        is_ignoring || (v["type"].as_str() == Some("segment") && ignores_dirty_submodules(&v["values"]))
+    })
+}
+
+
 pub struct Conf {
     c: Yaml,
     display_master: DisplayMaster,
 }
 
 impl Conf {
-    pub fn new(yaml: Yaml, display_master: DisplayMaster) -> Conf {
+    pub fn new(yaml: Yaml, mut display_master: DisplayMaster) -> Conf {
         let y_ref = &yaml;
         let version = &y_ref["version"];
         if version.is_badvalue() || version.is_null() {
@@ -233,6 +416,9 @@ impl Conf {
             },
             None => panic!("'version' should be string: {:?}", version),
         }
+        if ignores_dirty_submodules(&yaml["values"]) {
+            display_master.exclude_submodules();
+        }
         Conf { c: yaml.clone(), display_master: display_master }
     }
 
@@ -246,67 +432,110 @@ impl Conf {
             panic!("No values to display.");
         }
         let values = values_yaml.as_vec().unwrap();
+        let powerline = Powerline::new(&self.c["powerline"], self.display_master.get_shell());
+        render_values(&mut self.display_master, values, &powerline, false).0
+    }
+}
 
-        // FIXME: all of this logic should live outside of this module
-        // first pass: figure out what every value renders into
-        let mut items: Vec<Item> = Vec::new();
-        for v in values {
-            let simple_value = SimpleValue::new(v);
-            if simple_value.value_type == "separator" {
-                let separator = Separator::new(v, &simple_value);
-                items.push(Item::Separator {
-                    text: separator.display().unwrap(),
-                    always: separator.is_display_always(),
-                });
-            } else {
-                items.push(Item::Value(self.display_master.display_value(v, &simple_value)));
+// the rendered values and whether any of them is displayed; in_segment for the values of a segment
+fn render_values(display_master: &mut DisplayMaster, values: &[Yaml], powerline: &Powerline,
+                 in_segment: bool) -> (String, bool) {
+    // FIXME: all of this logic should live outside of this module
+    // first pass: figure out what every value renders into
+    let mut items: Vec<Item> = Vec::new();
+    for v in values {
+        let simple_value = SimpleValue::new(v);
+        if simple_value.value_type == "separator" {
+            let separator = Separator::new(v, &simple_value);
+            items.push(Item::Separator {
+                text: separator.display().unwrap(),
+                always: separator.is_display_always(),
+            });
+        } else if simple_value.value_type == "segment" {
+            // the end of an inner segment would reset the background in the middle of the outer one
+            if in_segment {
+                panic!("segment can't be placed in another segment: {:?}", v);
             }
+            let segment_values = match v["values"].as_vec() {
+                Some(vs) => vs,
+                None => panic!("segment needs to have 'values' specified"),
+            };
+            let (text, is_displayed) = render_values(display_master, segment_values, powerline, true);
+            items.push(Item::Segment {
+                text: if is_displayed {
+                    Some(format_value(&simple_value.pre_format, &simple_value.post_format, &text))
+                } else {
+                    None
+                },
+                colors: SegmentColors::new(v),
+            });
+        } else {
+            items.push(Item::Value(display_master.display_value(v, &simple_value)));
         }
+    }
 
-        // second pass: separators know now whether they are surrounded by values;
-        // out of a series of separators with no value in between only the last one is displayed
-        let mut display_it: Vec<bool> = vec!(false; items.len());
-        let mut pending_separator: Option<usize> = None;
-        for (idx, item) in items.iter().enumerate() {
-            let is_displayed = match *item {
-                Item::Value(ref value) => value.is_some(),
-                Item::Separator { always, .. } => {
-                    if !always {
-                        // a separator which comes later, still with no value in between,
-                        // replaces this one
-                        if is_surrounded(&items, idx) {
-                            pending_separator = Some(idx);
-                        }
-                        continue;
+    // second pass: separators know now whether they are surrounded by values;
+    // out of a series of separators with no value in between only the last one is displayed
+    let mut display_it: Vec<bool> = vec!(false; items.len());
+    let mut pending_separator: Option<usize> = None;
+    for (idx, item) in items.iter().enumerate() {
+        let is_displayed = match *item {
+            Item::Value(ref value) | Item::Segment { text: ref value, .. } => value.is_some(),
+            Item::Separator { always, .. } => {
+                if !always {
+                    // a separator which comes later, still with no value in between,
+                    // replaces this one
+                    if is_surrounded(&items, idx) {
+                        pending_separator = Some(idx);
                     }
-                    true
-                },
-            };
-            if !is_displayed {
-                continue;
-            }
-            display_it[idx] = true;
-            if let Some(p) = pending_separator.take() {
-                display_it[p] = true;
-            }
+                    continue;
+                }
+                true
+            },
+        };
+        if !is_displayed {
+            continue;
         }
-        if let Some(p) = pending_separator {
+        display_it[idx] = true;
+        if let Some(p) = pending_separator.take() {
             display_it[p] = true;
         }
+    }
+    if let Some(p) = pending_separator {
+        display_it[p] = true;
+    }
 
-        let mut out: String = String::new();
-        for (idx, item) in items.iter().enumerate() {
-            if !display_it[idx] {
-                continue;
-            }
-            match *item {
-                Item::Value(Some(ref s)) => out += s,
-                Item::Value(None) => (),
-                Item::Separator { ref text, .. } => out += text,
-            }
+    let mut out: String = String::new();
+    let mut has_value = false;
+    // the segment displayed last, until something else is displayed after it
+    let mut open_segment: Option<SegmentColors> = None;
+    for (idx, item) in items.iter().enumerate() {
+        if !display_it[idx] {
+            continue;
+        }
+        if let Item::Segment { text: Some(ref s), colors } = *item {
+            out += &powerline.start(open_segment.as_ref(), &colors);
+            out += s;
+            open_segment = Some(colors);
+            has_value = true;
+            continue;
+        }
+        if let Some(previous) = open_segment.take() {
+            out += &powerline.end(&previous);
+        }
+        match *item {
+            Item::Value(Some(ref s)) => {
+                out += s;
+                has_value = true;
+            },
+            Item::Value(None) | Item::Segment { .. } => (),
+            Item::Separator { ref text, .. } => out += text,
         }
-        out
     }
+    if let Some(previous) = open_segment {
+        out += &powerline.end(&previous);
+    }
+    (out, has_value)
 }
 
 pub fn load_configuration_from_file<P: AsRef<Path>>(path: P) -> Result<String, io::Error> {
@@ -321,11 +550,6 @@ pub fn load_configuration_from_file<P: AsRef<Path>>(path: P) -> Result<String, i
     }
 }
 
-// main function to obtain Conf struct
-pub fn get_configuration(supplied_conf_path: Option<String>, display_master: DisplayMaster) -> Conf {
-    Conf::new(get_configuration_yaml(supplied_conf_path), display_master)
-}
-
 // the config file as it is on disk; the default config when there is none
 pub fn get_configuration_content(supplied_conf_path: Option<String>) -> String {
     if supplied_conf_path.is_some() {
@@ -353,8 +577,8 @@ pub fn get_configuration_content(supplied_conf_path: Option<String>) -> String {
 }
 
 // parsed config file, ready to be handed over to Conf
-pub fn get_configuration_yaml(supplied_conf_path: Option<String>) -> Yaml {
-    let docs = YamlLoader::load_from_str(&get_configuration_content(supplied_conf_path)).unwrap();
+pub fn parse_configuration(content: &str) -> Yaml {
+    let docs = YamlLoader::load_from_str(content).unwrap();
     docs[0].clone()
 }
 
@@ -385,15 +609,17 @@ pub fn create_default_config(path: &PathBuf) -> Result<String, io::Error> {
 mod tests {
     // We'll use this git repo for testing
     use std::env;
-    use std::fs::{File,OpenOptions};
+    use std::fs::{self,File,OpenOptions};
     use std::io::{Read};
     use std::path::{Path};
     use std::process::{Command,Stdio};
-    use conf::{get_configuration,create_default_config,DEFAULT_CONF,Conf};
-    use constants::{get_default_config_path,ENV_LOCK};
+    use conf::{get_configuration_content,parse_configuration,create_default_config,DEFAULT_CONF,Conf};
+    use constants::{get_default_config_path,CACHE_FILE_NAME,CHANGED_KEY,ENV_LOCK,NEW_KEY,STAGED_KEY};
     use yaml_rust::{YamlLoader};
-    use backend::Backend;
+    use backend::{Backend,DemoData,StashInfo};
+    use colors::{self,Shell};
     use models::DisplayMaster;
+    use util::now;
     use git2::{Repository};
     use tempfile::TempDir;
 
@@ -433,6 +659,30 @@ mod tests {
     }
 
 
+    // run git in the repository created by init_git!
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .expect("Failed to execute git");
+        assert!(status.success(), "git {:?} failed with status: {}", args, status);
+    }
+
+    // render the config for the repository in dir
+    fn render(dir: &Path, config_text: &str) -> String {
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let repo = Repository::discover(dir).unwrap();
+        let backend = Backend::new(repo, true);
+        let dm: DisplayMaster = DisplayMaster::new(backend, true);
+        let mut c = Conf::new(docs[0].clone(), dm);
+        c.populate_values()
+    }
+
+
     #[test]
     #[should_panic(expected = "'version' is missing in config file.")]
     fn test_empty_config() {
@@ -502,7 +752,7 @@ values: []";
         let repo = Repository::discover(dir.path()).unwrap();
         let backend = Backend::new(repo, true);
         let dm: DisplayMaster = DisplayMaster::new(backend, true);
-        let mut c = get_configuration(None, dm);
+        let mut c = Conf::new(parse_configuration(&get_configuration_content(None)), dm);
         // the freshly created default config renders just the branch name
         // in a repository with a single commit and no remote
         assert_eq!(c.populate_values(), "master");
@@ -532,6 +782,1126 @@ values:
         assert_eq!(c.populate_values(), "#v1.0.0");
     }
 
+    #[test]
+    fn test_describe_is_displayed() {
+        let config_text = "version: '1'
+values:
+    - type: describe
+      pattern: 'v*'
+      pre_format: '<TAG>+<DISTANCE>'
+      post_format: ''";
+
+        init_git!(dir);
+        git(dir.path(), &["tag", "v1.0.0"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+        git(dir.path(), &["tag", "not-a-release"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "third"]);
+
+        assert_eq!(render(dir.path(), config_text), "v1.0.0+2");
+    }
+
+    #[test]
+    fn test_describe_annotated_only() {
+        let config_text = "version: '1'
+values:
+    - type: describe
+      annotated_only: true
+      pre_format: '<TAG>'
+      post_format: ''";
+
+        init_git!(dir);
+        git(dir.path(), &["tag", "v1.0.0"]);
+
+        // a lightweight tag is not considered
+        assert_eq!(render(dir.path(), config_text), "");
+    }
+
+    #[test]
+    fn test_detached_head_names() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: true
+      detached_head: ['tag', 'branch', 'hash']
+      detached_marker: '➦'
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<DETACHED><LOCAL_BRANCH>'
+          post_format: ''";
+
+        init_git!(dir);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "third"]);
+        git(dir.path(), &["checkout", "-q", "HEAD~2"]);
+        assert_eq!(render(dir.path(), config_text), "➦master~2");
+
+        git(dir.path(), &["tag", "v1.0.0"]);
+        assert_eq!(render(dir.path(), config_text), "➦v1.0.0");
+
+        git(dir.path(), &["checkout", "-q", "master"]);
+        assert_eq!(render(dir.path(), config_text), "master");
+    }
+
+    #[test]
+    fn test_detached_head_behind_merge() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: true
+      detached_head: ['branch']
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<LOCAL_BRANCH>'
+          post_format: ''";
+
+        init_git!(dir);
+        let path = dir.path();
+        git(path, &["checkout", "-q", "-b", "side"]);
+        for message in &["one", "two", "three"] {
+            git(path, &["commit", "--allow-empty", "-m", message]);
+        }
+        git(path, &["checkout", "-q", "master"]);
+        git(path, &["merge", "--no-ff", "-m", "merge side", "side"]);
+        git(path, &["branch", "-D", "side"]);
+        git(path, &["commit", "--allow-empty", "-m", "after merge"]);
+        // the commits merged in don't count, master~2 resolves to the commit
+        git(path, &["checkout", "-q", "master~2"]);
+        assert_eq!(render(path, config_text), "master~2");
+    }
+
+    #[test]
+    fn test_branch_name_rewrite_and_truncate() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          rewrite:
+            - pattern: '^feature/'
+              replacement: 'f/'
+            - pattern: '(JIRA-[0-9]+)-.*$'
+              replacement: '$1'
+          pre_format: '<LOCAL_BRANCH>'
+          post_format: ''
+        - type: name
+          max_length: 12
+          truncate: 'left'
+          pre_format: ' <LOCAL_BRANCH>'
+          post_format: ''";
+
+        init_git!(dir);
+        git(dir.path(), &["checkout", "-q", "-b", "feature/JIRA-12345-refactor-the-entire-payment-service"]);
+
+        assert_eq!(render(dir.path(), config_text), "f/JIRA-12345 …ent-service");
+    }
+
+    #[test]
+    fn test_branch_value() {
+        let config_text = "version: '1'
+values:
+    - type: branch
+      max_length: 8
+      pre_format: '['
+      post_format: ']'
+      detached_format:
+        pre_format: '➦'
+        post_format: ''
+      unborn_format:
+        pre_format: ''
+        post_format: ' (no commits)'
+    - type: remote_difference
+      display_if_uptodate: false
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<LOCAL_BRANCH>'
+          post_format: ''";
+
+        init_git!(dir);
+        // remote_difference is hidden, the branch is not
+        assert_eq!(render(dir.path(), config_text), "[master]");
+
+        git(dir.path(), &["checkout", "-q", "--orphan", "feature/fresh"]);
+        assert_eq!(render(dir.path(), config_text), "feature… (no commits)");
+
+        git(dir.path(), &["checkout", "-q", "--detach", "master"]);
+        let rendered = render(dir.path(), config_text);
+        assert!(rendered.starts_with("➦"));
+        assert_eq!(rendered.chars().count(), 8);
+    }
+
+    #[test]
+    fn test_upstream_gone() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: true
+      gone_format:
+        pre_format: '<LOCAL_BRANCH>'
+        post_format: ' ✝'
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<LOCAL_BRANCH>:<UPSTREAM_STATE>'
+          post_format: ''";
+
+        init_git!(dir);
+        let origin = TempDir::new().unwrap();
+        git(origin.path(), &["init", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        git(dir.path(), &["push", "origin", "master"]);
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "feature:none");
+
+        git(dir.path(), &["push", "-u", "origin", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "feature:");
+
+        git(dir.path(), &["push", "origin", "--delete", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "feature ✝");
+    }
+
+    #[test]
+    fn test_push_target() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<REMOTE>'
+          post_format: ''
+        - type: ahead
+          pre_format: '↑'
+          post_format: ''
+    - type: separator
+      display: always
+      pre_format: '│'
+      post_format: ''
+    - type: remote_difference
+      target: push
+      display_if_uptodate: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<REMOTE>'
+          post_format: ''
+        - type: ahead
+          pre_format: '↑'
+          post_format: ''";
+
+        init_git!(dir);
+        let upstream = TempDir::new().unwrap();
+        let origin = TempDir::new().unwrap();
+        git(upstream.path(), &["init", "--bare", "."]);
+        git(origin.path(), &["init", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "upstream", upstream.path().to_str().unwrap()]);
+        git(dir.path(), &["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        git(dir.path(), &["config", "remote.pushDefault", "origin"]);
+        git(dir.path(), &["push", "upstream", "master"]);
+        git(dir.path(), &["checkout", "-q", "-b", "feature", "--track", "upstream/master"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+        git(dir.path(), &["push", "origin", "feature"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "third"]);
+
+        assert_eq!(render(dir.path(), config_text), "upstream↑2│origin↑1");
+    }
+
+    #[test]
+    fn test_default_branch_of_remote() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      remote_branch: 'default_branch'
+      display_if_uptodate: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<REMOTE>/<REMOTE_BRANCH>'
+          post_format: ''
+        - type: ahead
+          pre_format: '↑'
+          post_format: ''";
+
+        init_git!(dir);
+        let origin = TempDir::new().unwrap();
+        git(origin.path(), &["init", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "origin", origin.path().to_str().unwrap()]);
+        git(dir.path(), &["push", "origin", "master:develop", "master:main"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+        // there is no upstream remote and origin/HEAD is not set
+        assert_eq!(render(dir.path(), config_text), "origin/main↑1");
+
+        git(dir.path(), &["remote", "set-head", "origin", "develop"]);
+        assert_eq!(render(dir.path(), config_text), "origin/develop↑1");
+    }
+
+    #[test]
+    fn test_remote_branch_glob() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      remote_branch: ['*/main', 'upstream/release/*']
+      group_separator: ' '
+      max_items: 3
+      display_if_uptodate: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: name
+          pre_format: '<REMOTE>/<REMOTE_BRANCH>'
+          post_format: ''
+        - type: behind
+          pre_format: '↓'
+          post_format: ''";
+
+        init_git!(dir);
+        let mut remotes: Vec<TempDir> = Vec::new();
+        for name in &["alice", "bob", "upstream"] {
+            let remote = TempDir::new().unwrap();
+            git(remote.path(), &["init", "--bare", "."]);
+            git(dir.path(), &["remote", "add", name, remote.path().to_str().unwrap()]);
+            remotes.push(remote);
+        }
+        git(dir.path(), &["push", "alice", "master:main"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+        git(dir.path(), &["push", "bob", "master:main"]);
+        git(dir.path(), &["push", "upstream", "master:release/1", "master:release/2"]);
+        git(dir.path(), &["reset", "-q", "--hard", "HEAD~1"]);
+
+        assert_eq!(render(dir.path(), config_text),
+                   "alice/main bob/main↓1 upstream/release/1↓1");
+    }
+
+    #[test]
+    fn test_fork_point() {
+        let config_text = "version: '1'
+values:
+    - type: fork_point
+      base: 'master'
+      merged_format:
+        pre_format: 'merged into <BASE>'
+        post_format: ''
+      pre_format: '<COMMITS> since <BASE>, <BEHIND> behind'
+      post_format: ''";
+
+        init_git!(dir);
+        // nothing to tell on the base branch itself
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "feature 1"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "feature 2"]);
+        git(dir.path(), &["checkout", "-q", "master"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "master 1"]);
+        git(dir.path(), &["checkout", "-q", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "2 since master, 1 behind");
+
+        git(dir.path(), &["checkout", "-q", "master"]);
+        git(dir.path(), &["merge", "-q", "--no-edit", "feature"]);
+        git(dir.path(), &["checkout", "-q", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "merged into master");
+    }
+
+    #[test]
+    fn test_head_commit() {
+        let config_text = "version: '1'
+values:
+    - type: head_commit
+      subject_length: 9
+      merge_marker: 'M '
+      thresholds:
+        - min: 0
+          pre_format: 'fresh <MERGE><SUBJECT> by <AUTHOR_INITIALS>'
+          post_format: ''
+        - min: '1d'
+          pre_format: 'stale'
+          post_format: ''
+      pre_format: '<AGE>'
+      post_format: ''";
+
+        init_git!(dir);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "a rather long subject\n\nand a body"]);
+        assert_eq!(render(dir.path(), config_text), "fresh a rather… by GPP");
+
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        git(dir.path(), &["commit", "--allow-empty", "-m", "feature"]);
+        git(dir.path(), &["checkout", "-q", "master"]);
+        git(dir.path(), &["merge", "-q", "--no-ff", "-m", "merge", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "fresh M merge by GPP");
+    }
+
+    #[test]
+    fn test_unpushed_tags() {
+        let config_text = "version: '1'
+values:
+    - type: unpushed_tags
+      pre_format: '<TAGS> ('
+      post_format: ')'";
+
+        init_git!(dir);
+        let remote = TempDir::new().unwrap();
+        git(remote.path(), &["init", "-q", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "origin", remote.path().to_str().unwrap()]);
+        git(dir.path(), &["tag", "v1"]);
+        // no remote branches, there is nowhere to push to
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["push", "-q", "origin", "master"]);
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["commit", "--allow-empty", "-m", "release"]);
+        git(dir.path(), &["tag", "-a", "-m", "v2", "v2"]);
+        assert_eq!(render(dir.path(), config_text), "v2 (1)");
+
+        // a different scope
+        let scoped = config_text.replace("post_format: ')'", "post_format: ')'\n      ref_scope: ['refs/heads/*']");
+        assert_eq!(render(dir.path(), &scoped), "");
+
+        git(dir.path(), &["push", "-q", "origin", "master"]);
+        assert_eq!(render(dir.path(), config_text), "");
+    }
+
+    #[test]
+    fn test_stash_branches() {
+        let config_text = "version: '1'
+values:
+    - type: stash
+      on_branch_marker: '*'
+      pre_format: '<ON_BRANCH>'
+      post_format: ''";
+
+        init_git!(dir);
+        fs::write(dir.path().join("file.txt"), "text").unwrap();
+        git(dir.path(), &["add", "file.txt"]);
+        git(dir.path(), &["commit", "-m", "file"]);
+        fs::write(dir.path().join("file.txt"), "stashed on master").unwrap();
+        git(dir.path(), &["stash"]);
+        assert_eq!(render(dir.path(), config_text), "*1");
+
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        assert_eq!(render(dir.path(), config_text), "1");
+        let current_only = config_text.replace("      on_branch_marker", "      current_branch_only: true\n      on_branch_marker");
+        assert_eq!(render(dir.path(), &current_only), "");
+
+        fs::write(dir.path().join("file.txt"), "stashed on feature").unwrap();
+        git(dir.path(), &["stash", "push", "-m", "feature work"]);
+        assert_eq!(render(dir.path(), config_text), "*2");
+        assert_eq!(render(dir.path(), &current_only), "*1");
+    }
+
+    #[test]
+    fn test_stash_age() {
+        let config_text = "version: '1'
+values:
+    - type: stash
+      current_branch_only: true
+      pre_format: '<AGE> '
+      post_format: ''";
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let render_demo = |stashes: Vec<StashInfo>| {
+            let mut demo = DemoData::new("master");
+            demo.stashes = stashes;
+            let dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+        // the ages are far from a change of the unit, a second passing during the test doesn't matter
+        let hour_and_half = StashInfo{ branch: Some(String::from("feature")), time: now() - 90 * 60 };
+        let days = StashInfo{ branch: Some(String::from("master")), time: now() - 84 * 3600 };
+        // the newest one of those counted
+        assert_eq!(render_demo(vec!(hour_and_half.clone(), days.clone())), "3d 1");
+        let mut on_master = hour_and_half;
+        on_master.branch = Some(String::from("master"));
+        assert_eq!(render_demo(vec!(on_master, days)), "1h 2");
+    }
+
+    #[test]
+    fn test_submodules() {
+        let config_text = "version: '1'
+values:
+    - type: submodules
+      pre_format: 'M<MODIFIED> U<UNINITIALIZED> S<OUT_OF_SYNC>'
+      post_format: ''
+    - type: changed
+      pre_format: ' Δ'
+      post_format: ''";
+
+        init_git!(dir);
+        assert_eq!(render(dir.path(), config_text), "");
+
+        init_git!(sub);
+        git(dir.path(), &["-c", "protocol.file.allow=always", "submodule", "add", "-q",
+                          sub.path().to_str().unwrap(), "sub"]);
+        git(dir.path(), &["commit", "-m", "add submodule"]);
+        assert_eq!(render(dir.path(), config_text), "");
+
+        fs::write(dir.path().join("sub").join("file.txt"), "text").unwrap();
+        assert_eq!(render(dir.path(), config_text), "M1 U0 S0 Δ1");
+        // the status doesn't look into the submodule either
+        let ignore_dirty = config_text.replace("    - type: submodules", "    - type: submodules\n      ignore_dirty: true");
+        assert_eq!(render(dir.path(), &ignore_dirty), "");
+
+        git(&dir.path().join("sub"), &["-c", "user.name=Prompter", "-c", "user.email=prompter@example.com",
+                                       "commit", "--allow-empty", "-m", "newer"]);
+        assert_eq!(render(dir.path(), &ignore_dirty), "M0 U0 S1");
+
+        // a fresh clone doesn't check out submodules
+        let clone = TempDir::new().unwrap();
+        git(clone.path(), &["clone", "-q", dir.path().to_str().unwrap(), "."]);
+        assert_eq!(render(clone.path(), config_text), "M0 U1 S0");
+    }
+
+    #[test]
+    fn test_worktree() {
+        let config_text = "version: '1'
+values:
+    - type: worktree
+      main_format:
+        pre_format: 'main +<OTHERS>'
+        post_format: ''
+      pre_format: '<NAME> +<OTHERS> L<LOCKED> P<PRUNABLE>'
+      post_format: ''";
+
+        init_git!(dir);
+        assert_eq!(render(dir.path(), config_text), "");
+
+        let trees = TempDir::new().unwrap();
+        let linked = trees.path().join("linked");
+        let locked = trees.path().join("locked");
+        git(dir.path(), &["worktree", "add", "-q", "-b", "linked", linked.to_str().unwrap()]);
+        assert_eq!(render(dir.path(), config_text), "main +1");
+        assert_eq!(render(&linked, config_text), "linked +1 L0 P0");
+
+        git(dir.path(), &["worktree", "add", "-q", "--lock", "-b", "locked", locked.to_str().unwrap()]);
+        assert_eq!(render(&linked, config_text), "linked +2 L1 P0");
+
+        fs::remove_dir_all(&locked).unwrap();
+        git(dir.path(), &["worktree", "unlock", "locked"]);
+        assert_eq!(render(&linked, config_text), "linked +2 L0 P1");
+    }
+
+    #[test]
+    fn test_repo_flags() {
+        let config_text = "version: '1'
+values:
+    - type: repo_flags
+      shallow_format:
+        pre_format: 'shallow '
+        post_format: ''
+      sparse_format:
+        pre_format: 'sparse '
+        post_format: ''
+      partial_format:
+        pre_format: 'partial '
+        post_format: ''
+      bare_format:
+        pre_format: 'bare '
+        post_format: ''
+      pre_format: '['
+      post_format: ']'";
+
+        init_git!(dir);
+        assert_eq!(render(dir.path(), config_text), "");
+        git(dir.path(), &["commit", "--allow-empty", "-m", "second"]);
+
+        let clone = TempDir::new().unwrap();
+        let url = format!("file://{}", dir.path().to_str().unwrap());
+        git(clone.path(), &["clone", "-q", "--depth", "1", &url, "."]);
+        assert_eq!(render(clone.path(), config_text), "[shallow ]");
+
+        git(clone.path(), &["config", "core.sparseCheckout", "true"]);
+        git(clone.path(), &["config", "remote.origin.promisor", "true"]);
+        assert_eq!(render(clone.path(), config_text), "[shallow sparse partial ]");
+
+        let bare = TempDir::new().unwrap();
+        git(bare.path(), &["init", "-q", "--bare", "."]);
+        assert_eq!(render(bare.path(), config_text), "[bare ]");
+    }
+
+    #[test]
+    fn test_remote_host() {
+        let config_text = "version: '1'
+values:
+    - type: remote_host
+      icons:
+        gitlab: 'GL'
+      pre_format: '<ICON> <PROVIDER> <HOST> <OWNER>/<REPO> <BRANCH>'
+      post_format: ''";
+
+        init_git!(dir);
+        // no remote at all
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["remote", "add", "origin", "git@gitlab.example.com:group/sub/project.git"]);
+        assert_eq!(render(dir.path(), config_text), "GL gitlab gitlab.example.com group/sub/project master");
+
+        git(dir.path(), &["remote", "add", "fork", "https://github.com/someone/project"]);
+        let fork = config_text.replace("    - type: remote_host", "    - type: remote_host\n      remote: fork\n      link: true");
+        let render_zsh = || {
+            let docs = YamlLoader::load_from_str(&fork).unwrap();
+            let repo = Repository::discover(dir.path()).unwrap();
+            let mut dm: DisplayMaster = DisplayMaster::new(Backend::new(repo, true), true);
+            dm.set_shell(Shell::Zsh);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+        // master is not on the remote, there is nothing to link to
+        assert_eq!(render_zsh(), " github github.com someone/project master");
+
+        // what the terminal gets as the target of the link
+        let link_target = |prompt: String| {
+            let rendered = colors::render(&prompt, Shell::Zsh, true);
+            let start = rendered.find("\x1b]8;;").unwrap() + "\x1b]8;;".len();
+            let end = rendered[start..].find('\x1b').unwrap() + start;
+            rendered[start..end].to_string()
+        };
+        git(dir.path(), &["update-ref", "refs/remotes/fork/feature/foo", "HEAD"]);
+        git(dir.path(), &["branch", "-q", "--set-upstream-to", "fork/feature/foo"]);
+        assert_eq!(link_target(render_zsh()), "https://github.com/someone/project/tree/feature/foo");
+
+        // the branch is tracked under a different name, which needs to be encoded in the URL
+        git(dir.path(), &["update-ref", "refs/remotes/fork/wip/100%done#2", "HEAD"]);
+        git(dir.path(), &["branch", "-q", "--set-upstream-to", "fork/wip/100%done#2"]);
+        assert_eq!(render_zsh(),
+                   " github github.com someone/project \
+                    %{\x1b]8;;https://github.com/someone/project/tree/wip/100%%25done%%232\x1b\\%}master\
+                    %{\x1b]8;;\x1b\\%}");
+        assert_eq!(link_target(render_zsh()), "https://github.com/someone/project/tree/wip/100%25done%232");
+    }
+
+    #[test]
+    fn test_git_config() {
+        let config_text = "version: '1'
+values:
+    - type: git_config
+      pre_format: '<KEY>='
+      post_format: ''
+    - type: git_config
+      key: core.nonexistent
+      pre_format: ''
+      post_format: ''";
+
+        init_git!(dir);
+        assert_eq!(render(dir.path(), config_text), "user.email=pretty-git-prompt@example.com");
+    }
+
+    #[test]
+    fn test_identity_mismatch() {
+        let config_text = "version: '1'
+values:
+    - type: git_config
+      identity_mismatch:
+        '*.corp.com': 'corp.com'
+        github.com: '*example.com'
+      pre_format: 'wrong: '
+      post_format: ''";
+
+        init_git!(dir);
+        // no remote, no host to compare with
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["remote", "add", "origin", "git@git.corp.com:team/project.git"]);
+        assert_eq!(render(dir.path(), config_text), "wrong: pretty-git-prompt@example.com");
+
+        git(dir.path(), &["config", "user.email", "me@corp.com"]);
+        assert_eq!(render(dir.path(), config_text), "");
+
+        git(dir.path(), &["remote", "set-url", "origin", "https://github.com/me/project"]);
+        assert_eq!(render(dir.path(), config_text), "wrong: me@corp.com");
+
+        // hosts without a rule are fine
+        git(dir.path(), &["remote", "set-url", "origin", "https://example.org/me/project"]);
+        assert_eq!(render(dir.path(), config_text), "");
+    }
+
+    #[test]
+    fn test_repo_name() {
+        let config_text = "version: '1'
+values:
+    - type: repo_name
+      pre_format: '['
+      post_format: ']'
+    - type: repo_name
+      source: remote
+      pre_format: ''
+      post_format: ''";
+
+        init_git!(dir);
+        let name = dir.path().file_name().unwrap().to_str().unwrap().to_string();
+        assert_eq!(render(dir.path(), config_text), format!("[{}]{}", name, name));
+
+        git(dir.path(), &["remote", "add", "origin", "https://github.com/someone/project.git"]);
+        assert_eq!(render(dir.path(), config_text), format!("[{}]project", name));
+    }
+
+    #[test]
+    fn test_repo_path() {
+        let config_text = "version: '1'
+values:
+    - type: repo_path
+      max_depth: 2
+      pre_format: '/'
+      post_format: ''";
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let render_demo = |relative_dir: &str| {
+            let mut demo = DemoData::new("master");
+            demo.relative_dir = relative_dir.to_string();
+            let dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+
+        assert_eq!(render_demo(""), "");
+        assert_eq!(render_demo("src"), "/src");
+        assert_eq!(render_demo("src/models"), "/src/models");
+        assert_eq!(render_demo("tests/integration/data"), "/…/integration/data");
+    }
+
+    #[test]
+    fn test_lfs() {
+        let config_text = "version: '1'
+values:
+    - type: lfs
+      pre_format: 'P<POINTERS> L<LOCKED>'
+      post_format: ''";
+
+        init_git!(dir);
+        let path = dir.path();
+        fs::write(path.join("image.bin"), "version https://git-lfs.github.com/spec/v1\noid sha256:4d7a\nsize 12345\n").unwrap();
+        fs::write(path.join("design.psd"), "layers").unwrap();
+        fs::write(path.join(".gitattributes"), "*.sh text eol=lf\n").unwrap();
+        git(path, &["add", "image.bin", "design.psd", ".gitattributes"]);
+        git(path, &["commit", "-m", "files"]);
+        // LFS is not used
+        assert_eq!(render(path, config_text), "");
+
+        fs::write(path.join(".gitattributes"), "*.bin filter=lfs diff=lfs merge=lfs -text\n*.psd lockable\n").unwrap();
+        git(path, &["add", ".gitattributes"]);
+        git(path, &["commit", "-m", "lfs"]);
+        assert_eq!(render(path, config_text), "P1 L0");
+
+        // the lock cache of git-lfs after locking design.psd: the version of the store, then
+        // a map from the path and the id of the lock to the lock
+        let mut lock_cache: Vec<u8> = vec!(3, 4, 0, 2);
+        let mut map: Vec<u8> = vec!(0xff, 0x82, 0, 2);
+        for key in &["design.psd", "*id*://1"] {
+            map.push(key.len() as u8);
+            map.extend_from_slice(key.as_bytes());
+            map.push(13);
+            map.extend_from_slice(b"*locking.Lock");
+            map.extend_from_slice(&[0xff, 0x84, 3, 1, 2, 3]);
+        }
+        lock_cache.push(map.len() as u8);
+        lock_cache.extend(map);
+        fs::create_dir(path.join(".git").join("lfs")).unwrap();
+        fs::write(path.join(".git").join("lfs").join("lockcache.db"), &lock_cache).unwrap();
+        assert_eq!(render(path, config_text), "P1 L1");
+
+        fs::write(path.join("image.bin"), "the real content").unwrap();
+        assert_eq!(render(path, config_text), "P0 L1");
+
+        fs::write(path.join(".git").join("lfs").join("lockcache.db"), "").unwrap();
+        assert_eq!(render(path, config_text), "");
+    }
+
+    #[test]
+    fn test_lfs_attributes_outside_of_root() {
+        let config_text = "version: '1'
+values:
+    - type: lfs
+      pre_format: 'P<POINTERS> L<LOCKED>'
+      post_format: ''";
+
+        init_git!(dir);
+        let path = dir.path();
+        fs::create_dir(path.join("assets")).unwrap();
+        fs::write(path.join("assets").join(".gitattributes"), "*.bin filter=lfs\n").unwrap();
+        fs::write(path.join("assets").join("image.bin"), "version https://git-lfs.github.com/spec/v1\n").unwrap();
+        fs::write(path.join("data.csv"), "a,b").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "files"]);
+        assert_eq!(render(path, config_text), "P1 L0");
+
+        fs::remove_file(path.join("assets").join("image.bin")).unwrap();
+        fs::write(path.join(".git").join("info").join("attributes"), "*.csv filter=lfs\n").unwrap();
+        fs::write(path.join("data.csv"), "version https://git-lfs.github.com/spec/v1\n").unwrap();
+        assert_eq!(render(path, config_text), "P1 L0");
+    }
+
+    #[test]
+    fn test_conflict_files() {
+        let config_text = "version: '1'
+values:
+    - type: conflicts
+      max_files: 2
+      pre_format: '<FILES> M<BOTH_MODIFIED> A<ADDED_BY_BOTH> U<DELETED_BY_US> T<DELETED_BY_THEM> ✖'
+      post_format: ''";
+
+        init_git!(dir);
+        let path = dir.path();
+        for name in &["modified.txt", "deleted.txt"] {
+            fs::write(path.join(name), "base").unwrap();
+        }
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "base"]);
+        git(path, &["checkout", "-q", "-b", "feature"]);
+        fs::write(path.join("modified.txt"), "feature").unwrap();
+        fs::write(path.join("added.txt"), "feature").unwrap();
+        git(path, &["rm", "-q", "deleted.txt"]);
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "feature"]);
+        git(path, &["checkout", "-q", "master"]);
+        fs::write(path.join("modified.txt"), "master").unwrap();
+        fs::write(path.join("added.txt"), "master").unwrap();
+        fs::write(path.join("deleted.txt"), "master").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-m", "master"]);
+        // the merge fails because of the conflicts
+        let status = Command::new("git").arg("-C").arg(path).args(["merge", "feature"])
+            .stdout(Stdio::null()).stderr(Stdio::null()).status().unwrap();
+        assert!(!status.success());
+
+        assert_eq!(render(path, config_text), "added.txt,deleted.txt,… M1 A1 U0 T1 ✖3");
+    }
+
+    #[test]
+    fn test_walk_limit_and_cache() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: false
+      walk_limit: 3
+      cache: true
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: ahead
+          pre_format: '↑'
+          post_format: ''
+        - type: behind
+          pre_format: '↓'
+          post_format: ''";
+
+        init_git!(dir);
+        let remote = TempDir::new().unwrap();
+        git(remote.path(), &["init", "-q", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "origin", remote.path().to_str().unwrap()]);
+        git(dir.path(), &["push", "-q", "-u", "origin", "master"]);
+        for i in 0..5 {
+            git(dir.path(), &["commit", "--allow-empty", "-m", &format!("local {}", i)]);
+        }
+        assert_eq!(render(dir.path(), config_text), "↑3+");
+        assert_eq!(render(dir.path(), &config_text.replace("walk_limit: 3", "walk_limit: 5")), "↑5");
+
+        // the counts come from the cache as long as the branches stay where they are
+        let cache_path = dir.path().join(".git").join(CACHE_FILE_NAME);
+        let cache = fs::read_to_string(&cache_path).unwrap();
+        assert_eq!(cache.lines().count(), 2);
+        fs::write(&cache_path, cache.replace(" 3+ 0", " 2 1")).unwrap();
+        assert_eq!(render(dir.path(), config_text), "↑2↓1");
+        // a line which can't be parsed, e.g. cut short, is counted again
+        fs::write(&cache_path, cache.replace(" 3+ 0", " 3+ x")).unwrap();
+        assert_eq!(render(dir.path(), config_text), "↑3+");
+        // the cache is replaced by renaming a temporary file, nothing is left behind
+        let cache_files = fs::read_dir(dir.path().join(".git")).unwrap()
+            .filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().starts_with(CACHE_FILE_NAME))
+            .count();
+        assert_eq!(cache_files, 1);
+
+        git(dir.path(), &["commit", "--allow-empty", "-m", "one more"]);
+        assert_eq!(render(dir.path(), config_text), "↑3+");
+    }
+
+    #[test]
+    fn test_walk_limit_stops_early() {
+        let config_text = "version: '1'
+values:
+    - type: remote_difference
+      display_if_uptodate: false
+      walk_limit: 3
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: ahead
+          pre_format: '↑'
+          post_format: ''
+        - type: behind
+          pre_format: '↓'
+          post_format: ''";
+
+        init_git!(dir);
+        let remote = TempDir::new().unwrap();
+        git(remote.path(), &["init", "-q", "--bare", "."]);
+        git(dir.path(), &["remote", "add", "origin", remote.path().to_str().unwrap()]);
+        git(dir.path(), &["push", "-q", "-u", "origin", "master"]);
+        let commit_at = |message: &str, seconds: usize| {
+            let date = format!("{} +0000", 1500000000 + seconds);
+            let status = Command::new("git")
+                .arg("-C").arg(dir.path())
+                .args(["commit", "-q", "--allow-empty", "-m", message])
+                .env("GIT_AUTHOR_DATE", &date)
+                .env("GIT_COMMITTER_DATE", &date)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        // ten commits on both sides, the commits of both branches take turns
+        git(dir.path(), &["checkout", "-q", "-b", "other"]);
+        for i in 0..10 {
+            commit_at(&format!("remote {}", i), 2 * i + 1);
+        }
+        git(dir.path(), &["push", "-q", "origin", "other:master"]);
+        git(dir.path(), &["checkout", "-q", "master"]);
+        for i in 0..10 {
+            commit_at(&format!("local {}", i), 2 * i + 2);
+        }
+        assert_eq!(render(dir.path(), config_text), "↑3+↓3+");
+
+        // the walk doesn't get anywhere near the oldest commits: it works even without them
+        let repo = Repository::open(dir.path()).unwrap();
+        for branch in &["master~9", "origin/master~9"] {
+            let oid = repo.revparse_single(branch).unwrap().id().to_string();
+            fs::remove_file(dir.path().join(".git").join("objects").join(&oid[..2]).join(&oid[2..])).unwrap();
+        }
+        assert_eq!(render(dir.path(), config_text), "↑3+↓3+");
+        // while counting all of them fails
+        let no_limit = config_text.replace("      walk_limit: 3\n", "");
+        assert_eq!(render(dir.path(), &no_limit), "");
+    }
+
+    #[test]
+    fn test_number_options() {
+        let config_text = "version: '1'
+values:
+    - type: new
+      number:
+        compact: true
+      pre_format: '+'
+      post_format: ''
+    - type: changed
+      number:
+        cap: 99
+      pre_format: ' Δ'
+      post_format: ''
+    - type: staged
+      number:
+        show_zero: true
+        zero_format:
+          pre_format: ' ✔'
+          post_format: ''
+      pre_format: ' ▶'
+      post_format: ''
+    - type: conflicts
+      number:
+        show_zero: true
+      pre_format: ' ✖<FILES>M<BOTH_MODIFIED>A<ADDED_BY_BOTH>U<DELETED_BY_US>T<DELETED_BY_THEM>:'
+      post_format: ''
+    - type: stash
+      number:
+        hide_count: true
+      pre_format: ' ☐'
+      post_format: ''
+    - type: remote_difference
+      display_if_uptodate: true
+      walk_limit: 500
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: ahead
+          number:
+            compact: true
+          pre_format: ' ↑'
+          post_format: ''
+        - type: behind
+          pre_format: '↓'
+          post_format: ''";
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let render_demo = |demo: DemoData| {
+            let dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+
+        let mut demo = DemoData::new("master");
+        demo.file_statuses.insert(NEW_KEY.to_string(), 1520);
+        demo.file_statuses.insert(CHANGED_KEY.to_string(), 120);
+        demo.stashes.push(StashInfo{ branch: None, time: 0 });
+        demo.ahead = 2000;
+        demo.behind = 3;
+        assert_eq!(render_demo(demo), "+1.5k Δ99+ ✔ ✖M0A0U0T0:0 ☐ ↑500+↓3");
+
+        let mut demo = DemoData::new("master");
+        demo.file_statuses.insert(NEW_KEY.to_string(), 12400);
+        demo.file_statuses.insert(STAGED_KEY.to_string(), 2);
+        assert_eq!(render_demo(demo), "+12k ▶2 ✖M0A0U0T0:0");
+    }
+
+    #[test]
+    fn test_clean() {
+        let config_text = "version: '1'
+values:
+    - type: branch
+      pre_format: ''
+      post_format: ''
+    - type: separator
+      display: surrounded
+      pre_format: '│'
+      post_format: ''
+    - type: changed
+      pre_format: 'Δ'
+      post_format: ''
+    - type: clean
+      include_stash: true
+      pre_format: '✔'
+      post_format: ''";
+
+        init_git!(dir);
+        let path = dir.path();
+        fs::write(path.join("file.txt"), "content").unwrap();
+        git(path, &["add", "file.txt"]);
+        git(path, &["commit", "-m", "file"]);
+        assert_eq!(render(path, config_text), "master│✔");
+
+        fs::write(path.join("file.txt"), "changed").unwrap();
+        assert_eq!(render(path, config_text), "master│Δ1");
+
+        git(path, &["stash"]);
+        assert_eq!(render(path, config_text), "master");
+        assert_eq!(render(path, &config_text.replace("include_stash: true", "include_stash: false")),
+                   "master│✔");
+    }
+
+    #[test]
+    fn test_count_thresholds() {
+        let config_text = "version: '1'
+values:
+    - type: changed
+      pre_format: '<g>Δ'
+      post_format: '</g>'
+      thresholds:
+        - min: 20
+          pre_format: '<r>Δ'
+          post_format: '</r>'
+        - min: 5
+          pre_format: '<y>Δ'
+          post_format: '</y>'
+    - type: remote_difference
+      display_if_uptodate: false
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: behind
+          pre_format: '↓'
+          post_format: ''
+          thresholds:
+            - min: 51
+              pre_format: '<r>↓'
+              post_format: '</r>'";
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let render_demo = |changed: u32, behind: usize| {
+            let mut demo = DemoData::new("master");
+            demo.file_statuses.insert(CHANGED_KEY.to_string(), changed);
+            demo.behind = behind;
+            let dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+        assert_eq!(render_demo(4, 50), "<g>Δ4</g>↓50");
+        assert_eq!(render_demo(5, 51), "<y>Δ5</y><r>↓51</r>");
+        assert_eq!(render_demo(120, 0), "<r>Δ120</r>");
+    }
+
+    #[test]
+    fn test_powerline_segments() {
+        let config_text = "version: '1'
+values:
+    - type: segment
+      background: 'blue'
+      foreground: 15
+      pre_format: ' '
+      post_format: ' '
+      values:
+        - type: branch
+          pre_format: ''
+          post_format: ''
+    - type: segment
+      background: 3
+      pre_format: ' '
+      post_format: ' '
+      values:
+        - type: changed
+          pre_format: 'Δ'
+          post_format: ''
+        - type: stash
+          pre_format: '☐'
+          post_format: ''
+    - type: new
+      pre_format: ' ✚'
+      post_format: ''";
+        let render_demo = |config: &str, changed: u32, shell: Shell| {
+            let docs = YamlLoader::load_from_str(config).unwrap();
+            let mut demo = DemoData::new("master");
+            demo.file_statuses.insert(CHANGED_KEY.to_string(), changed);
+            let mut dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+            dm.set_shell(shell);
+            Conf::new(docs[0].clone(), dm).populate_values()
+        };
+        assert_eq!(render_demo(config_text, 2, Shell::Zsh),
+                   "%{%K{4}%}%{%F{15}%} master %{%F{4}%}%{%K{3}%}\u{e0b0}%{%f%} Δ2 \
+                    %{%F{3}%}%{%k%}\u{e0b0}%{%f%}");
+        // a segment with no value displayed is left out
+        assert_eq!(render_demo(config_text, 0, Shell::Bash),
+                   "\\[\\e[48;5;4m\\]\\[\\e[38;5;15m\\] master \
+                    \\[\\e[38;5;4m\\]\\[\\e[49m\\]\u{e0b0}\\[\\e[39m\\]");
+
+        let ascii_config = config_text.replacen("values:\n", "powerline:\n    ascii: true\nvalues:\n", 1);
+        assert_eq!(colors::render(&render_demo(&ascii_config, 2, Shell::Zsh), Shell::Zsh, false),
+                   " master > Δ2 >");
+    }
+
+    #[test]
+    #[should_panic(expected = "segment can't be placed in another segment")]
+    fn test_nested_segment() {
+        let config_text = "version: '1'
+values:
+    - type: segment
+      background: 'blue'
+      pre_format: ''
+      post_format: ''
+      values:
+        - type: segment
+          background: 'red'
+          pre_format: ''
+          post_format: ''
+          values:
+            - type: branch
+              pre_format: ''
+              post_format: ''";
+        let docs = YamlLoader::load_from_str(config_text).unwrap();
+        let dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(DemoData::new("master"), true), true);
+        Conf::new(docs[0].clone(), dm).populate_values();
+    }
+
+    #[test]
+    fn test_powerline_preset() {
+        let docs = YamlLoader::load_from_str(include_str!("../files/pretty-git-prompt-powerline.yml")).unwrap();
+        let mut demo = DemoData::new("master");
+        demo.ahead = 1;
+        demo.file_statuses.insert(NEW_KEY.to_string(), 2);
+        let mut dm: DisplayMaster = DisplayMaster::new(Backend::new_demo(demo, true), true);
+        dm.set_shell(Shell::Zsh);
+        let prompt = Conf::new(docs[0].clone(), dm).populate_values();
+        assert_eq!(colors::render(&prompt, Shell::Zsh, false), " master ↑1 \u{e0b0} ✚2 \u{e0b0}");
+    }
+
     #[test]
     #[should_panic(expected = "Config should be using version '1', instead is using '0'")]
     fn test_lower_version() {
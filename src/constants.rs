@@ -18,6 +18,39 @@ pub static CONFLICTS_KEY: &'static str = "conflicts";
 // displayed instead of <REMOTE_FIRST_LETTER> when there is no remote counterpart
 pub static NO_REMOTE_PLACEHOLDER: &'static str = "_";
 
+// `remote_branch` set to this compares against the default branch of a remote
+pub static DEFAULT_BRANCH_KEYWORD: &str = "default_branch";
+// remotes tried, in this order, when `remote_branch` contains <REMOTE>
+pub static DEFAULT_REMOTES: [&str; 2] = ["upstream", "origin"];
+// names of the default branch tried when the remote doesn't have HEAD set
+pub static DEFAULT_BRANCH_NAMES: [&str; 2] = ["main", "master"];
+
+// minimal length of an abbreviated commit hash
+pub static DEFAULT_HASH_LENGTH: usize = 7;
+
+// references a tag needs to be reachable from to count as pushed
+pub static DEFAULT_REF_SCOPE: &str = "refs/remotes/*";
+
+// config key git_config displays
+pub static DEFAULT_CONFIG_KEY: &str = "user.email";
+
+// counts of commits ahead and behind are cached in this file in the git directory
+pub static CACHE_FILE_NAME: &str = "pretty-git-prompt.cache";
+pub static CACHE_MAX_ENTRIES: usize = 64;
+
+// the number of conflicting files listed in <FILES>
+pub static DEFAULT_MAX_FILES: usize = 3;
+
+// Git LFS pointer files are tiny text files starting with the version of the spec
+pub static LFS_POINTER_PREFIX: &str = "version https://git-lfs.github.com/spec/v1";
+pub static LFS_POINTER_MAX_SIZE: u64 = 1024;
+// git-lfs keeps the locks you hold in this file in the git directory
+pub static LFS_LOCK_CACHE: &str = "lfs/lockcache.db";
+
+// glyphs between powerline segments: the one of the Powerline fonts and one for any font
+pub static POWERLINE_SEPARATOR: &str = "\u{e0b0}";
+pub static POWERLINE_ASCII_SEPARATOR: &str = ">";
+
 pub static CURRENT_CONFIG_VERSION: &'static str = "1";
 
 pub static DEFAULT_CONFIG_NAME: &'static str = "pretty-git-prompt.yml";
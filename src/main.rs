@@ -4,6 +4,7 @@
 
 extern crate clap;
 extern crate git2;
+extern crate regex;
 #[cfg(test)]
 extern crate tempfile;
 extern crate yaml_rust;
@@ -11,14 +12,14 @@ extern crate yaml_rust;
 use std::io::{self, Write};
 use backend::Backend;
 use colors::{Shell,colors_wanted,list_colors};
-use conf::{Conf,get_configuration,get_configuration_content,create_default_config};
+use conf::{Conf,get_configuration_content,parse_configuration,create_default_config};
 use constants::*;
 use models::{DisplayMaster};
 use preview::{preview_demo,preview_repo};
 
 use git2::Repository;
 use clap::{Arg, ArgAction, ArgMatches, Command};
-use yaml_rust::{Yaml,YamlLoader};
+use yaml_rust::Yaml;
 
 // util mod def needs to be first b/c of macro definitions and usage in other modules
 #[macro_use]
@@ -102,7 +103,7 @@ fn run_preview(sub_matches: &ArgMatches, conf_path: Option<String>, debug: bool)
     let colors = colors_wanted(sub_matches.get_flag("no-color"));
     let content = get_configuration_content(conf_path);
     let shell = resolve_shell(sub_matches.get_one::<String>("shell"), Some(&content));
-    let yaml: Yaml = YamlLoader::load_from_str(&content).unwrap()[0].clone();
+    let yaml: Yaml = parse_configuration(&content);
 
     let stdout = io::stdout();
     let mut handle = stdout.lock();
@@ -123,6 +124,25 @@ fn run_preview(sub_matches: &ArgMatches, conf_path: Option<String>, debug: bool)
     exit_on_write_error(result);
 }
 
+// every conflicting file, `git status` style
+fn run_conflicts(debug: bool) {
+    let repo = match Repository::discover(".") {
+        Ok(repo) => repo,
+        Err(e) => {
+            if debug { println!("This is not a git repository: {:?}", e); }
+            writeln!(io::stderr(), "This is not a git repository.").ok();
+            ::std::process::exit(1);
+        }
+    };
+    let backend = Backend::new(repo, debug);
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    for conflict in backend.get_conflicts() {
+        let label = format!("{}:", conflict.kind.description());
+        exit_on_write_error(writeln!(handle, "{:17}{}", label, conflict.path));
+    }
+}
+
 fn main() {
     let def_conf_desc: String = format!("Create default config at \"{}\".", get_default_config_path().to_str().unwrap());
     let matches = Command::new("pretty-git-prompt")
@@ -135,6 +155,8 @@ fn main() {
             .about("List colors and text styles with the codes to put in a config file.")
             .arg(shell_arg())
             .arg(no_color_arg()))
+        .subcommand(Command::new("conflicts")
+            .about("List the conflicting files and how they conflict."))
         .subcommand(Command::new("preview")
             .about("Render the prompt in this terminal, the way your shell would.")
             .arg(Arg::new("demo")
@@ -185,6 +207,7 @@ fn main() {
             };
         },
         Some(("list-colors", sub_matches)) => run_list_colors(sub_matches),
+        Some(("conflicts", _sub_matches)) => run_conflicts(debug_enabled),
         Some(("preview", sub_matches)) => {
             run_preview(sub_matches, config_path(&matches, sub_matches), debug_enabled)
         },
@@ -200,9 +223,10 @@ fn main() {
             };
 
             let backend = Backend::new(repo, debug_enabled);
-            let dm: DisplayMaster = DisplayMaster::new(backend, debug_enabled);
-            let conf_path = matches.get_one::<String>("config");
-            let mut conf: Conf = get_configuration(conf_path.cloned(), dm);
+            let mut dm: DisplayMaster = DisplayMaster::new(backend, debug_enabled);
+            let content = get_configuration_content(matches.get_one::<String>("config").cloned());
+            dm.set_shell(resolve_shell(None, Some(&content)));
+            let mut conf: Conf = Conf::new(parse_configuration(&content), dm);
             let out: String = conf.populate_values();
             println!("{}", out);
         }
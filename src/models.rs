@@ -4,9 +4,13 @@
  */
 use std::collections::HashMap;
 
-use backend::{Backend,RemoteBranch,BranchAheadBehind};
+use backend::{Backend,RemoteBranch,BranchAheadBehind,CompareTarget,ConflictKind,DetachedName,StashInfo,UpstreamState,
+              WalkOptions};
+use colors::{Shell,hyperlink};
 use constants::*;
+use util::{encode_url_path,format_age,now,parse_duration,parse_remote_url};
 
+use regex::{self,Regex};
 use yaml_rust::{Yaml};
 
 
@@ -19,11 +23,135 @@ fn substiute_special_values(s: String, values: &HashMap<String, String>) -> Stri
     r
 }
 
+// cut s down to max_length characters, the ellipsis counts into the length unless it doesn't
+// fit at all
+fn truncate(s: &str, max_length: usize, ellipsis: &str, left: bool) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= max_length {
+        return s.to_string();
+    }
+    let ellipsis_length = ellipsis.chars().count();
+    let (ellipsis, kept) = if ellipsis_length < max_length {
+        (ellipsis, max_length - ellipsis_length)
+    } else {
+        ("", max_length)
+    };
+    if left {
+        let tail: String = chars[chars.len() - kept..].iter().collect();
+        format!("{}{}", ellipsis, tail)
+    } else {
+        let head: String = chars[..kept].iter().collect();
+        format!("{}{}", head, ellipsis)
+    }
+}
+
+// a list of strings in config, a single string stands for a list with a single item
+fn get_string_list(value_yaml: &Yaml, key: &str) -> Option<Vec<String>> {
+    match value_yaml[key] {
+        Yaml::String(ref s) => Some(vec!(s.clone())),
+        Yaml::Array(ref v) => Some(v.iter().map(|item| match item.as_str() {
+            Some(s) => s.to_string(),
+            None => panic!("`{}` needs to be a list of strings: {:?}", key, v),
+        }).collect()),
+        Yaml::BadValue | Yaml::Null => None,
+        ref other => panic!("`{}` needs to be a list of strings: {:?}", key, other),
+    }
+}
+
 pub fn format_value(pre_format: &str, post_format: &str, data: &str) -> String {
     format!("{}{}{}", pre_format, data, post_format)
 }
 
 
+fn remote_branch(remote_name: &str, remote_branch_name: &str) -> RemoteBranch {
+    RemoteBranch{
+        remote_branch: format!("{}/{}", remote_name, remote_branch_name),
+        remote_name: remote_name.to_string(),
+        remote_branch_name: remote_branch_name.to_string(),
+    }
+}
+
+// a shell glob as a regular expression matching whole strings
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).unwrap()
+}
+
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+fn parse_remote_branch(s: &str) -> RemoteBranch {
+    let v: Vec<&str> = s.splitn(2, '/').collect();
+    if v.len() != 2 {
+        panic!("`remote_branch` must be in form of `<REMOTE>/<BRANCH>`");
+    }
+    remote_branch(v[0], v[1])
+}
+
+
+// how <REMOTE> and HEAD in the name of a remote branch are resolved
+#[derive(Debug, Clone)]
+struct RemoteCandidates {
+    // tried for <REMOTE>
+    remotes: Vec<String>,
+    // tried for HEAD when a remote doesn't have it set
+    default_branch_names: Vec<String>,
+}
+
+impl RemoteCandidates {
+    fn new(value_yaml: &Yaml) -> RemoteCandidates {
+        let remotes = get_string_list(value_yaml, "remotes")
+            .unwrap_or_else(|| DEFAULT_REMOTES.iter().map(|r| r.to_string()).collect());
+        if remotes.is_empty() {
+            panic!("`remotes` in {:?} can't be empty", value_yaml);
+        }
+        let default_branch_names = get_string_list(value_yaml, "default_branch_names")
+            .unwrap_or_else(|| DEFAULT_BRANCH_NAMES.iter().map(|r| r.to_string()).collect());
+        RemoteCandidates{ remotes, default_branch_names }
+    }
+
+    // the first remote branch which exists out of the ones spec stands for;
+    // the first candidate when there is none
+    fn resolve(&self, backend: &Backend, spec: &str) -> RemoteBranch {
+        let spec = if spec == DEFAULT_BRANCH_KEYWORD { "<REMOTE>/HEAD" } else { spec };
+        let remotes: Vec<String> = if spec.contains("<REMOTE>") {
+            self.remotes.clone()
+        } else {
+            vec!(parse_remote_branch(spec).remote_name)
+        };
+        let mut candidates: Vec<RemoteBranch> = Vec::new();
+        for remote in remotes {
+            let b = parse_remote_branch(&spec.replace("<REMOTE>", &remote));
+            if b.remote_branch_name != "HEAD" {
+                candidates.push(b);
+                continue;
+            }
+            match backend.get_remote_default_branch(&remote, &self.default_branch_names) {
+                Some(name) => candidates.push(remote_branch(&remote, &name)),
+                None => candidates.push(b),
+            }
+        }
+        for b in &candidates {
+            if backend.remote_branch_exists(b) {
+                log!(backend, "{} resolved to {}", spec, b.remote_branch);
+                return b.clone();
+            }
+        }
+        log!(backend, "No remote branch exists for {}", spec);
+        candidates[0].clone()
+    }
+}
+
+
 // this is the minimum amount of required attributes of a value
 #[derive(Debug, Clone)]
 pub struct SimpleValue {
@@ -53,6 +181,317 @@ impl SimpleValue {
 }
 
 
+// formats used instead of the ones of the value in a specific situation
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub pre_format: String,
+    pub post_format: String,
+}
+
+impl Format {
+    // None when the value has no such format
+    pub fn new(value_yaml: &Yaml, key: &str) -> Option<Format> {
+        let format_yaml = &value_yaml[key];
+        if format_yaml.is_badvalue() || format_yaml.is_null() {
+            return None;
+        }
+        Some(Format::from_yaml(format_yaml, key))
+    }
+
+    // pre_format and post_format of format_yaml, which is described by context in errors
+    fn from_yaml(format_yaml: &Yaml, context: &str) -> Format {
+        let pre_format = match format_yaml["pre_format"].as_str() {
+            Some(s) => s.to_string(),
+            None => panic!("pre_format in {} {:?} is not specified", context, format_yaml),
+        };
+        let post_format = match format_yaml["post_format"].as_str() {
+            Some(s) => s.to_string(),
+            None => panic!("post_format in {} {:?} is not specified", context, format_yaml),
+        };
+        Format{ pre_format, post_format }
+    }
+
+    // substitute the special values in both formats
+    fn substitute(&self, special_values: &HashMap<String, String>) -> Format {
+        Format{
+            pre_format: substiute_special_values(self.pre_format.clone(), special_values),
+            post_format: substiute_special_values(self.post_format.clone(), special_values),
+        }
+    }
+
+    fn from_value(value: &SimpleValue) -> Format {
+        Format{ pre_format: value.pre_format.clone(), post_format: value.post_format.clone() }
+    }
+
+    fn display(&self, data: &str) -> String {
+        format_value(&self.pre_format, &self.post_format, data)
+    }
+}
+
+
+// how a count is displayed, from the 'number' options of a value
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormat {
+    // larger counts are displayed as <CAP>+
+    cap: Option<usize>,
+    // 1.2k, 12k, 3.4M
+    compact: bool,
+    show_zero: bool,
+    // used for 0 instead of the formats of the value, without the count
+    zero_format: Option<Format>,
+    // just the formats, e.g. a symbol, without the count
+    hide_count: bool,
+    // formats used instead of the ones of the value for larger counts
+    thresholds: Vec<Threshold>,
+}
+
+impl NumberFormat {
+    pub fn new(value_yaml: &Yaml) -> NumberFormat {
+        let thresholds = Threshold::list(value_yaml, |y| y.as_i64());
+        let number_yaml = &value_yaml["number"];
+        if number_yaml.is_badvalue() || number_yaml.is_null() {
+            return NumberFormat{ thresholds, ..NumberFormat::default() };
+        }
+        let cap = match number_yaml["cap"].as_i64() {
+            Some(c) if c > 0 => Some(c as usize),
+            Some(c) => panic!("`cap` needs to be a positive number, not {}", c),
+            None => None,
+        };
+        NumberFormat{
+            cap,
+            compact: number_yaml["compact"].as_bool().unwrap_or(false),
+            show_zero: number_yaml["show_zero"].as_bool().unwrap_or(false),
+            zero_format: Format::new(number_yaml, "zero_format"),
+            hide_count: number_yaml["hide_count"].as_bool().unwrap_or(false),
+            thresholds,
+        }
+    }
+
+    fn compact(count: usize) -> String {
+        for &(unit, suffix) in &[(1_000_000, "M"), (1_000, "k")] {
+            if count >= unit {
+                // rounded down, 1999 is not 2k yet
+                let tenths = count * 10 / unit;
+                return if tenths < 100 && !tenths.is_multiple_of(10) {
+                    format!("{}.{}{}", tenths / 10, tenths % 10, suffix)
+                } else {
+                    format!("{}{}", tenths / 10, suffix)
+                };
+            }
+        }
+        count.to_string()
+    }
+
+    // the count as text; capped means there are more than count, counting stopped
+    fn text(&self, count: usize, capped: bool) -> String {
+        if self.hide_count {
+            return String::new();
+        }
+        let (count, capped) = match self.cap {
+            Some(cap) if count > cap => (cap, true),
+            _ => (count, capped),
+        };
+        let text = if self.compact { NumberFormat::compact(count) } else { count.to_string() };
+        if capped { format!("{}+", text) } else { text }
+    }
+
+    // None when the count is 0 and zero is not displayed
+    fn display(&self, format: &Format, count: usize, capped: bool,
+               special_values: &HashMap<String, String>) -> Option<String> {
+        if count == 0 && !capped {
+            if !self.show_zero {
+                return None;
+            }
+            if let Some(ref f) = self.zero_format {
+                return Some(f.substitute(special_values).display(""));
+            }
+        }
+        let format = Threshold::pick(&self.thresholds, count as i64, format.clone());
+        Some(format.substitute(special_values).display(&self.text(count, capped)))
+    }
+}
+
+
+// formats used instead of the ones of the value once a number reaches min
+#[derive(Debug, Clone)]
+pub struct Threshold {
+    min: i64,
+    format: Format,
+}
+
+impl Threshold {
+    // the 'thresholds' list of the value; parse_min reads the 'min' of a threshold
+    fn list(value_yaml: &Yaml, parse_min: fn(&Yaml) -> Option<i64>) -> Vec<Threshold> {
+        let mut thresholds: Vec<Threshold> = Vec::new();
+        if let Some(v) = value_yaml["thresholds"].as_vec() {
+            for threshold_yaml in v {
+                let min = match parse_min(&threshold_yaml["min"]) {
+                    Some(m) => m,
+                    None => panic!("min in threshold {:?} is missing or invalid", threshold_yaml),
+                };
+                thresholds.push(Threshold{ min, format: Format::from_yaml(threshold_yaml, "threshold") });
+            }
+        }
+        thresholds
+    }
+
+    // format of the highest threshold number reaches, default when it reaches none
+    fn pick(thresholds: &[Threshold], number: i64, default: Format) -> Format {
+        let mut picked: Option<&Threshold> = None;
+        for t in thresholds {
+            if number >= t.min && picked.is_none_or(|p| t.min >= p.min) {
+                picked = Some(t);
+            }
+        }
+        match picked {
+            Some(t) => t.format.clone(),
+            None => default,
+        }
+    }
+}
+
+
+// how the branch name is displayed when HEAD is detached
+#[derive(Debug, Clone)]
+pub struct DetachedHead {
+    names: Vec<DetachedName>,
+    hash_length: usize,
+    // what <DETACHED> renders as
+    marker: String,
+}
+
+impl DetachedHead {
+    pub fn new(value_yaml: &Yaml) -> DetachedHead {
+        let name_strings = get_string_list(value_yaml, "detached_head").unwrap_or_default();
+        let names = name_strings.iter().map(|n| match DetachedName::from_name(n) {
+            Some(name) => name,
+            None => panic!("`detached_head` needs to be one of 'branch', 'tag' or 'hash', not '{}'", n),
+        }).collect();
+        let hash_length = match value_yaml["hash_length"].as_i64() {
+            Some(l) if l > 0 => l as usize,
+            Some(l) => panic!("`hash_length` needs to be a positive number, not {}", l),
+            None => DEFAULT_HASH_LENGTH,
+        };
+        let marker = value_yaml["detached_marker"].as_str().unwrap_or("").to_string();
+        DetachedHead{ names, hash_length, marker }
+    }
+
+    // name of the checked out commit, to be used when HEAD is detached
+    fn name(&self, backend: &Backend) -> Option<String> {
+        backend.get_detached_head_name(&self.names, self.hash_length)
+    }
+
+    fn marker(&self, backend: &Backend) -> String {
+        if backend.is_head_detached() {
+            self.marker.clone()
+        } else {
+            String::new()
+        }
+    }
+}
+
+
+// rewriting and shortening of a branch name
+#[derive(Debug, Clone)]
+pub struct BranchNameFormat {
+    // (regular expression, replacement), applied in order
+    rewrite: Vec<(Regex, String)>,
+    max_length: Option<usize>,
+    ellipsis: String,
+    // cut off the beginning of the name instead of the end
+    truncate_left: bool,
+}
+
+impl BranchNameFormat {
+    pub fn new(value_yaml: &Yaml) -> BranchNameFormat {
+        let mut rewrite: Vec<(Regex, String)> = Vec::new();
+        if let Some(rules) = value_yaml["rewrite"].as_vec() {
+            for rule in rules {
+                let pattern = match rule["pattern"].as_str() {
+                    Some(p) => p,
+                    None => panic!("pattern in rewrite rule {:?} is not specified", rule),
+                };
+                let replacement = match rule["replacement"].as_str() {
+                    Some(r) => r,
+                    None => panic!("replacement in rewrite rule {:?} is not specified", rule),
+                };
+                match Regex::new(pattern) {
+                    Ok(re) => rewrite.push((re, replacement.to_string())),
+                    Err(e) => panic!("Invalid pattern in rewrite rule '{}': {}", pattern, e),
+                }
+            }
+        }
+        let max_length = match value_yaml["max_length"].as_i64() {
+            Some(l) if l > 0 => Some(l as usize),
+            Some(l) => panic!("`max_length` needs to be a positive number, not {}", l),
+            None => None,
+        };
+        let ellipsis = value_yaml["ellipsis"].as_str().unwrap_or("…").to_string();
+        let truncate_left = match value_yaml["truncate"].as_str() {
+            Some("left") => true,
+            Some("right") | None => false,
+            Some(s) => panic!("`truncate` needs to be one of 'left' or 'right', not '{}'", s),
+        };
+        BranchNameFormat{ rewrite, max_length, ellipsis, truncate_left }
+    }
+
+    fn apply(&self, name: &str) -> String {
+        let mut r = name.to_string();
+        for (re, replacement) in &self.rewrite {
+            r = re.replace(&r, replacement.as_str()).into_owned();
+        }
+        match self.max_length {
+            Some(l) => truncate(&r, l, &self.ellipsis, self.truncate_left),
+            None => r,
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct BranchStatus<'a> {
+    name_format: BranchNameFormat,
+    detached_head: DetachedHead,
+    detached_format: Option<Format>,
+    unborn_format: Option<Format>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> BranchStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> BranchStatus<'a> {
+        BranchStatus{
+            value: simple_value.clone(), backend, debug,
+            name_format: BranchNameFormat::new(value_yaml),
+            detached_head: DetachedHead::new(value_yaml),
+            detached_format: Format::new(value_yaml, "detached_format"),
+            unborn_format: Format::new(value_yaml, "unborn_format"),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display branch, value: {:?}", self);
+        let mut format = Format::from_value(&self.value);
+        let name = if self.backend.is_head_detached() {
+            if let Some(ref f) = self.detached_format {
+                format = f.clone();
+            }
+            self.detached_head.name(self.backend)
+        } else {
+            if self.backend.is_head_unborn() {
+                if let Some(ref f) = self.unborn_format {
+                    format = f.clone();
+                }
+            }
+            self.backend.get_current_branch_name()
+        }?;
+        Some(format.display(&self.name_format.apply(&name)))
+    }
+}
+
+
 #[derive(Debug)]
 pub struct RepoStatus<'a> {
     debug: bool,
@@ -82,27 +521,47 @@ impl<'a> RepoStatus<'a> {
 
 #[derive(Debug)]
 pub struct StashStatus<'a> {
+    // count just the stashes created on the current branch
+    current_branch_only: bool,
+    on_branch_marker: String,
+    number: NumberFormat,
     debug: bool,
     backend: &'a mut Backend,
     value: SimpleValue,
 }
 
 impl<'a> StashStatus<'a> {
-    fn new(simple_value: &SimpleValue, backend: &'a mut Backend, debug: bool) -> StashStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue, backend: &'a mut Backend, debug: bool) -> StashStatus<'a> {
         StashStatus{
-            value: simple_value.clone(), backend: backend, debug: debug
+            value: simple_value.clone(), backend: backend, debug: debug,
+            current_branch_only: value_yaml["current_branch_only"].as_bool().unwrap_or(false),
+            on_branch_marker: value_yaml["on_branch_marker"].as_str().unwrap_or("").to_string(),
+            number: NumberFormat::new(value_yaml),
         }
     }
 
     fn display(&mut self) -> Option<String> {
         log!(self, "display repository state, value: {:?}", self);
-        let count = self.backend.get_stash_count();
-        if count > 0 {
-            return Some(format_value(&self.value.pre_format,
-                                     &self.value.post_format,
-                                     &format!("{}", count)));
+        let branch = self.backend.get_current_branch_name();
+        let mut stashes = self.backend.get_stashes();
+        let on_branch = |stash: &StashInfo| branch.is_some() && stash.branch == branch;
+        if self.current_branch_only {
+            stashes.retain(on_branch);
         }
-        None
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        // the newest comes first
+        let age = match stashes.first() {
+            Some(newest) => format_age(now() - newest.time),
+            None => String::new(),
+        };
+        special_values.insert("<AGE>".to_string(), age);
+        let marker = if stashes.iter().any(on_branch) {
+            self.on_branch_marker.clone()
+        } else {
+            String::new()
+        };
+        special_values.insert("<ON_BRANCH>".to_string(), marker);
+        self.number.display(&Format::from_value(&self.value), stashes.len(), false, &special_values)
     }
 }
 
@@ -129,8 +588,586 @@ impl<'a> TagStatus<'a> {
 }
 
 
+#[derive(Debug)]
+pub struct UnpushedTagsStatus<'a> {
+    // globs of references the tags need to be reachable from
+    ref_scope: Vec<String>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> UnpushedTagsStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> UnpushedTagsStatus<'a> {
+        let ref_scope = get_string_list(value_yaml, "ref_scope")
+            .unwrap_or_else(|| vec!(DEFAULT_REF_SCOPE.to_string()));
+        UnpushedTagsStatus{
+            value: simple_value.clone(), backend, debug, ref_scope
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display unpushed tags, value: {:?}", self);
+        let tags = self.backend.get_unpushed_tags(&self.ref_scope);
+        if tags.is_empty() {
+            return None;
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<TAGS>".to_string(), tags.join(","));
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            &format!("{}", tags.len())
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct SubmodulesStatus<'a> {
+    ignore_dirty: bool,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> SubmodulesStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> SubmodulesStatus<'a> {
+        SubmodulesStatus{
+            value: simple_value.clone(), backend, debug,
+            ignore_dirty: value_yaml["ignore_dirty"].as_bool().unwrap_or(false),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display submodules, value: {:?}", self);
+        let summary = self.backend.get_submodule_summary(self.ignore_dirty)?;
+        if summary.modified == 0 && summary.uninitialized == 0 && summary.out_of_sync == 0 {
+            return None;
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<MODIFIED>".to_string(), summary.modified.to_string());
+        special_values.insert("<UNINITIALIZED>".to_string(), summary.uninitialized.to_string());
+        special_values.insert("<OUT_OF_SYNC>".to_string(), summary.out_of_sync.to_string());
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            ""
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct WorktreeStatus<'a> {
+    // used in the main work tree when there are linked ones
+    main_format: Option<Format>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> WorktreeStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> WorktreeStatus<'a> {
+        WorktreeStatus{
+            value: simple_value.clone(), backend, debug,
+            main_format: Format::new(value_yaml, "main_format"),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display work tree, value: {:?}", self);
+        let info = self.backend.get_worktree_info()?;
+        let format = match info.name {
+            Some(_) => Format::from_value(&self.value),
+            None if info.others > 0 => self.main_format.clone()?,
+            None => return None,
+        };
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<NAME>".to_string(), info.name.clone().unwrap_or_default());
+        special_values.insert("<OTHERS>".to_string(), info.others.to_string());
+        special_values.insert("<LOCKED>".to_string(), info.locked.to_string());
+        special_values.insert("<PRUNABLE>".to_string(), info.prunable.to_string());
+        Some(format.substitute(&special_values).display(""))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct RepoFlagsStatus<'a> {
+    shallow_format: Option<Format>,
+    sparse_format: Option<Format>,
+    partial_format: Option<Format>,
+    bare_format: Option<Format>,
+    git_dir_format: Option<Format>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> RepoFlagsStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> RepoFlagsStatus<'a> {
+        RepoFlagsStatus{
+            value: simple_value.clone(), backend, debug,
+            shallow_format: Format::new(value_yaml, "shallow_format"),
+            sparse_format: Format::new(value_yaml, "sparse_format"),
+            partial_format: Format::new(value_yaml, "partial_format"),
+            bare_format: Format::new(value_yaml, "bare_format"),
+            git_dir_format: Format::new(value_yaml, "git_dir_format"),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display repository flags, value: {:?}", self);
+        let flags = self.backend.get_repo_flags();
+        let mut response = String::new();
+        for &(is_set, ref format) in &[(flags.shallow, &self.shallow_format),
+                                       (flags.sparse, &self.sparse_format),
+                                       (flags.partial, &self.partial_format),
+                                       (flags.bare, &self.bare_format),
+                                       (flags.in_git_dir, &self.git_dir_format)] {
+            if let (true, Some(f)) = (is_set, format) {
+                response += &f.display("");
+            }
+        }
+        if response.is_empty() {
+            return None;
+        }
+        Some(format_value(&self.value.pre_format, &self.value.post_format, &response))
+    }
+}
+
+
+// the kind of service hosting a repository, guessed from its host name
+fn hosting_provider(host: &str) -> &'static str {
+    if host.contains("github") {
+        "github"
+    } else if host.contains("gitlab") {
+        "gitlab"
+    } else if host.contains("bitbucket") {
+        "bitbucket"
+    } else if host.contains("gitea") || host == "codeberg.org" {
+        "gitea"
+    } else {
+        "self-hosted"
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoteHostStatus<'a> {
+    // None stands for the remote the current branch tracks
+    remote: Option<String>,
+    // provider -> icon
+    icons: HashMap<String, String>,
+    // make <BRANCH> a link to the branch in the web interface of the provider
+    link: bool,
+    shell: Shell,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> RemoteHostStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue, shell: Shell,
+           backend: &'a Backend, debug: bool) -> RemoteHostStatus<'a> {
+        let mut icons: HashMap<String, String> = HashMap::new();
+        match value_yaml["icons"] {
+            Yaml::Hash(ref h) => for (k, v) in h {
+                match (k.as_str(), v.as_str()) {
+                    (Some(provider), Some(icon)) => { icons.insert(provider.to_string(), icon.to_string()); },
+                    _ => panic!("`icons` needs to map providers to strings: {:?}: {:?}", k, v),
+                }
+            },
+            Yaml::BadValue | Yaml::Null => (),
+            ref other => panic!("`icons` needs to map providers to strings: {:?}", other),
+        }
+        RemoteHostStatus{
+            value: simple_value.clone(), backend, debug, shell, icons,
+            remote: value_yaml["remote"].as_str().map(|s| s.to_string()),
+            link: value_yaml["link"].as_bool().unwrap_or(false),
+        }
+    }
+
+    // web page of the branch, the layout of the URL differs between providers
+    fn branch_url(provider: &str, repo_url: &str, branch: &str) -> String {
+        let branch = encode_url_path(branch);
+        match provider {
+            "gitlab" => format!("{}/-/tree/{}", repo_url, branch),
+            "bitbucket" => format!("{}/src/{}", repo_url, branch),
+            "gitea" => format!("{}/src/branch/{}", repo_url, branch),
+            _ => format!("{}/tree/{}", repo_url, branch),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display remote host, value: {:?}", self);
+        let (remote_name, url) = self.backend.get_remote_url(self.remote.as_deref())?;
+        let remote_url = match parse_remote_url(&url) {
+            Some(u) => u,
+            None => {
+                log!(self, "Remote {} is not hosted anywhere: {}", remote_name, url);
+                return None;
+            }
+        };
+        let provider = hosting_provider(&remote_url.host);
+        let repo_url = format!("https://{}/{}/{}", remote_url.host, remote_url.owner, remote_url.repo);
+        let branch = self.backend.get_current_branch_name().unwrap_or_default();
+        // only the branch tracked on this remote has a page there, under its name on the remote
+        let upstream = if self.link && !branch.is_empty() && !self.backend.is_head_detached() {
+            self.backend.get_upstream_branch().filter(|(remote, _)| *remote == remote_name)
+        } else {
+            None
+        };
+        let branch = match upstream {
+            Some((_, ref upstream_branch)) =>
+                hyperlink(self.shell, &RemoteHostStatus::branch_url(provider, &repo_url, upstream_branch), &branch),
+            None => branch,
+        };
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<REMOTE>".to_string(), remote_name);
+        special_values.insert("<HOST>".to_string(), remote_url.host.clone());
+        special_values.insert("<OWNER>".to_string(), remote_url.owner.clone());
+        special_values.insert("<REPO>".to_string(), remote_url.repo.clone());
+        special_values.insert("<PROVIDER>".to_string(), provider.to_string());
+        special_values.insert("<ICON>".to_string(), self.icons.get(provider).cloned().unwrap_or_default());
+        special_values.insert("<URL>".to_string(), repo_url);
+        special_values.insert("<BRANCH>".to_string(), branch);
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            ""
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct GitConfigStatus<'a> {
+    key: String,
+    // host of the remote -> globs of the domain of the e-mail address expected for it
+    identity_mismatch: Option<Vec<(String, String)>>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> GitConfigStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> GitConfigStatus<'a> {
+        let identity_mismatch = match value_yaml["identity_mismatch"] {
+            Yaml::Hash(ref h) => Some(h.iter().map(|(k, v)| match (k.as_str(), v.as_str()) {
+                (Some(host), Some(domain)) => (host.to_string(), domain.to_string()),
+                _ => panic!("`identity_mismatch` needs to map hosts to domains: {:?}: {:?}", k, v),
+            }).collect()),
+            Yaml::BadValue | Yaml::Null => None,
+            ref other => panic!("`identity_mismatch` needs to map hosts to domains: {:?}", other),
+        };
+        GitConfigStatus{
+            value: simple_value.clone(), backend, debug, identity_mismatch,
+            key: value_yaml["key"].as_str().unwrap_or(DEFAULT_CONFIG_KEY).to_string(),
+        }
+    }
+
+    // the domain of the address doesn't match the rule for the host of the remote;
+    // there's nothing to compare with when no rule matches the host
+    fn is_mismatch(&self, rules: &[(String, String)], address: &str) -> bool {
+        let host = match self.backend.get_remote_url(None).and_then(|(_, url)| parse_remote_url(&url)) {
+            Some(u) => u.host,
+            None => return false,
+        };
+        let domain = address.rsplit('@').next().unwrap_or("").to_lowercase();
+        match rules.iter().find(|&(h, _)| glob_to_regex(&h.to_lowercase()).is_match(&host)) {
+            Some((_, d)) => !glob_to_regex(&d.to_lowercase()).is_match(&domain),
+            None => false,
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display git config, value: {:?}", self);
+        let config_value = self.backend.get_config_value(&self.key)?;
+        if let Some(ref rules) = self.identity_mismatch {
+            if !self.is_mismatch(rules, &config_value) {
+                return None;
+            }
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<KEY>".to_string(), self.key.clone());
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            &config_value
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct RepoNameStatus<'a> {
+    // the name of the repository on the remote rather than the name of the directory
+    from_remote: bool,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> RepoNameStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> RepoNameStatus<'a> {
+        let from_remote = match value_yaml["source"].as_str() {
+            Some("remote") => true,
+            Some("directory") | None => false,
+            Some(other) => panic!("`source` needs to be 'directory' or 'remote', not {:?}", other),
+        };
+        RepoNameStatus{
+            value: simple_value.clone(), backend, debug, from_remote
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display repository name, value: {:?}", self);
+        let from_remote = if self.from_remote {
+            self.backend.get_remote_url(None).and_then(|(_, url)| parse_remote_url(&url)).map(|u| u.repo)
+        } else {
+            None
+        };
+        // a repository without a remote still has a directory
+        let name = match from_remote {
+            Some(n) => n,
+            None => self.backend.get_repo_name()?,
+        };
+        Some(format_value(&self.value.pre_format, &self.value.post_format, &name))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct RepoPathStatus<'a> {
+    // the number of the innermost directories displayed
+    max_depth: Option<usize>,
+    ellipsis: String,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> RepoPathStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> RepoPathStatus<'a> {
+        let max_depth = match value_yaml["max_depth"].as_i64() {
+            Some(d) if d > 0 => Some(d as usize),
+            Some(d) => panic!("`max_depth` needs to be a positive number, not {}", d),
+            None => None,
+        };
+        RepoPathStatus{
+            value: simple_value.clone(), backend, debug, max_depth,
+            ellipsis: value_yaml["ellipsis"].as_str().unwrap_or("…").to_string(),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display path in repository, value: {:?}", self);
+        let mut components = self.backend.get_relative_dir()?;
+        // the root of the work tree
+        if components.is_empty() {
+            return None;
+        }
+        if let Some(max_depth) = self.max_depth {
+            if components.len() > max_depth {
+                components = components.split_off(components.len() - max_depth);
+                components.insert(0, self.ellipsis.clone());
+            }
+        }
+        Some(format_value(&self.value.pre_format, &self.value.post_format, &components.join("/")))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct LfsStatus<'a> {
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> LfsStatus<'a> {
+    fn new(simple_value: &SimpleValue, backend: &'a Backend, debug: bool) -> LfsStatus<'a> {
+        LfsStatus{
+            value: simple_value.clone(), backend, debug
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display LFS, value: {:?}", self);
+        let summary = self.backend.get_lfs_summary()?;
+        if summary.pointers == 0 && summary.locked == 0 {
+            return None;
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<POINTERS>".to_string(), summary.pointers.to_string());
+        special_values.insert("<LOCKED>".to_string(), summary.locked.to_string());
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            ""
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct DescribeStatus<'a> {
+    annotated_only: bool,
+    pattern: Option<String>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> DescribeStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> DescribeStatus<'a> {
+        let annotated_only = value_yaml["annotated_only"].as_bool().unwrap_or(false);
+        let pattern = value_yaml["pattern"].as_str().map(|s| s.to_string());
+        DescribeStatus{
+            value: simple_value.clone(), backend, debug, annotated_only, pattern
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display describe, value: {:?}", self);
+        let description = self.backend.get_description(self.annotated_only,
+                                                       self.pattern.as_deref())?;
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<TAG>".to_string(), description.tag);
+        special_values.insert("<DISTANCE>".to_string(), description.distance.to_string());
+        special_values.insert("<HASH>".to_string(), description.hash);
+        Some(format_value(
+            &substiute_special_values(self.value.pre_format.clone(), &special_values),
+            &substiute_special_values(self.value.post_format.clone(), &special_values),
+            ""
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct ForkPointStatus<'a> {
+    // the branch we forked off
+    base: String,
+    candidates: RemoteCandidates,
+    merged_format: Option<Format>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> ForkPointStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> ForkPointStatus<'a> {
+        let base = value_yaml["base"].as_str().unwrap_or(DEFAULT_BRANCH_KEYWORD).to_string();
+        ForkPointStatus{
+            value: simple_value.clone(), backend, debug, base,
+            candidates: RemoteCandidates::new(value_yaml),
+            merged_format: Format::new(value_yaml, "merged_format"),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display fork point, value: {:?}", self);
+        let base = if self.base == DEFAULT_BRANCH_KEYWORD || self.base.contains("<REMOTE>") {
+            self.candidates.resolve(self.backend, &self.base).remote_branch
+        } else {
+            self.base.clone()
+        };
+        let fork_point = self.backend.get_fork_point(&base)?;
+        // HEAD is the base itself
+        if fork_point.ahead == 0 && fork_point.behind == 0 {
+            return None;
+        }
+        let mut format = Format::from_value(&self.value);
+        if fork_point.ahead == 0 {
+            if let Some(ref f) = self.merged_format {
+                format = f.clone();
+            }
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<BASE>".to_string(), base);
+        special_values.insert("<COMMITS>".to_string(), fork_point.ahead.to_string());
+        special_values.insert("<BEHIND>".to_string(), fork_point.behind.to_string());
+        special_values.insert("<AGE>".to_string(), format_age(now() - fork_point.time));
+        Some(format_value(
+            &substiute_special_values(format.pre_format, &special_values),
+            &substiute_special_values(format.post_format, &special_values),
+            ""
+        ))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct HeadCommitStatus<'a> {
+    subject_length: Option<usize>,
+    merge_marker: String,
+    // by the age of the commit
+    thresholds: Vec<Threshold>,
+    debug: bool,
+    backend: &'a Backend,
+    value: SimpleValue,
+}
+
+impl<'a> HeadCommitStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
+           backend: &'a Backend, debug: bool) -> HeadCommitStatus<'a> {
+        let subject_length = match value_yaml["subject_length"].as_i64() {
+            Some(l) if l > 0 => Some(l as usize),
+            Some(l) => panic!("`subject_length` needs to be a positive number, not {}", l),
+            None => None,
+        };
+        HeadCommitStatus{
+            value: simple_value.clone(), backend, debug, subject_length,
+            merge_marker: value_yaml["merge_marker"].as_str().unwrap_or("").to_string(),
+            thresholds: Threshold::list(value_yaml, |y| match *y {
+                Yaml::Integer(i) => Some(i),
+                Yaml::String(ref s) => parse_duration(s),
+                _ => None,
+            }),
+        }
+    }
+
+    fn display(&self) -> Option<String> {
+        log!(self, "display HEAD commit, value: {:?}", self);
+        let commit = self.backend.get_head_commit()?;
+        let age = now() - commit.time;
+        let subject = match self.subject_length {
+            Some(l) => truncate(&commit.subject, l, "…", false),
+            None => commit.subject.clone(),
+        };
+        let initials: String = commit.author.split_whitespace()
+            .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+            .flat_map(|c| c.to_uppercase())
+            .collect();
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<AGE>".to_string(), format_age(age));
+        special_values.insert("<SUBJECT>".to_string(), subject);
+        special_values.insert("<AUTHOR>".to_string(), commit.author.clone());
+        special_values.insert("<AUTHOR_INITIALS>".to_string(), initials);
+        let merge = if commit.is_merge { self.merge_marker.clone() } else { String::new() };
+        special_values.insert("<MERGE>".to_string(), merge);
+        let format = Threshold::pick(&self.thresholds, age, Format::from_value(&self.value));
+        Some(format.substitute(&special_values).display(""))
+    }
+}
+
+
 #[derive(Debug)]
 pub struct FileStatus<'a> {
+    // how many conflicting files <FILES> lists
+    max_files: usize,
+    number: NumberFormat,
     debug: bool,
     backend: &'a mut Backend,
     value: SimpleValue,
@@ -149,7 +1186,8 @@ impl<'a> FileStatus<'a> {
             match h.get(&ft_string) {
                 Some(v) => {
                     let v_string: String = v.to_string();
-                    return s.get(&v_string).cloned();
+                    // only the kinds of files which are present are counted
+                    return Some(s.get(&v_string).cloned().unwrap_or(0));
                 },
                 None => panic!("Invalid name for file status: {}", &ft_string)
             };
@@ -157,34 +1195,105 @@ impl<'a> FileStatus<'a> {
         None
     }
 
-    fn new(simple_value: &SimpleValue, backend: &'a mut Backend, debug: bool) -> FileStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue, backend: &'a mut Backend, debug: bool) -> FileStatus<'a> {
+        let max_files = match value_yaml["max_files"].as_i64() {
+            Some(m) if m >= 0 => m as usize,
+            Some(m) => panic!("`max_files` can't be negative: {}", m),
+            None => DEFAULT_MAX_FILES,
+        };
         FileStatus{
-            value: simple_value.clone(), backend: backend, debug: debug
+            value: simple_value.clone(), backend: backend, debug: debug, max_files,
+            number: NumberFormat::new(value_yaml),
         }
     }
 
+    // the conflicting files by name and by kind
+    fn get_conflict_values(&self) -> HashMap<String, String> {
+        let conflicts = self.backend.get_conflicts();
+        let mut files: Vec<String> = conflicts.iter().take(self.max_files)
+            .map(|c| c.path.rsplit('/').next().unwrap_or(&c.path).to_string())
+            .collect();
+        if conflicts.len() > self.max_files {
+            files.push("…".to_string());
+        }
+        let mut special_values: HashMap<String, String> = HashMap::new();
+        special_values.insert("<FILES>".to_string(), files.join(","));
+        for &(placeholder, kind) in &[("<BOTH_MODIFIED>", ConflictKind::BothModified),
+                                      ("<ADDED_BY_BOTH>", ConflictKind::AddedByBoth),
+                                      ("<DELETED_BY_US>", ConflictKind::DeletedByUs),
+                                      ("<DELETED_BY_THEM>", ConflictKind::DeletedByThem)] {
+            let count = conflicts.iter().filter(|c| c.kind == kind).count();
+            special_values.insert(placeholder.to_string(), count.to_string());
+        }
+        special_values
+    }
+
     fn display(&mut self) -> Option<String> {
         log!(self, "display file state, value: {:?}", self);
         let vt = self.value.value_type.clone();
-        if let Some(x) = self.get_file_status_for_type(&vt) {
-            return Some(format_value(&self.value.pre_format,
-                                     &self.value.post_format,
-                                     &format!("{}", x)));
+        let count = self.get_file_status_for_type(&vt)? as usize;
+        // the placeholders of conflicts are filled even without any, for show_zero
+        let special_values = if vt == "conflicts" {
+            self.get_conflict_values()
+        } else {
+            HashMap::new()
+        };
+        self.number.display(&Format::from_value(&self.value), count, false, &special_values)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct CleanStatus<'a> {
+    // an empty stash is required as well
+    include_stash: bool,
+    debug: bool,
+    backend: &'a mut Backend,
+    value: SimpleValue,
+}
+
+impl<'a> CleanStatus<'a> {
+    fn new(value_yaml: &Yaml, simple_value: &SimpleValue, backend: &'a mut Backend, debug: bool) -> CleanStatus<'a> {
+        CleanStatus{
+            value: simple_value.clone(), backend, debug,
+            include_stash: value_yaml["include_stash"].as_bool().unwrap_or(false),
         }
-        None
+    }
+
+    fn display(&mut self) -> Option<String> {
+        log!(self, "display clean, value: {:?}", self);
+        let statuses = self.backend.get_file_status()?;
+        let dirty = [NEW_KEY, CHANGED_KEY, STAGED_KEY, CONFLICTS_KEY].iter()
+            .any(|key| statuses.get(*key).cloned().unwrap_or(0) > 0);
+        if dirty || (self.include_stash && !self.backend.get_stashes().is_empty()) {
+            return None;
+        }
+        Some(format_value(&self.value.pre_format, &self.value.post_format, ""))
     }
 }
 
 
 #[derive(Debug)]
 pub struct RemoteTracking<'a> {
-    remote_branch: Option<RemoteBranch>,
+    // <REMOTE>/<BRANCH>, where <REMOTE> can be the placeholder itself and <BRANCH> can be HEAD,
+    // or a glob matching remote branches
+    remote_branch: Vec<String>,
+    // displayed between groups of values for different remote branches
+    group_separator: String,
+    max_items: Option<usize>,
+    candidates: RemoteCandidates,
+    target: CompareTarget,
+    walk: WalkOptions,
     display_if_uptodate: bool,
     no_remote_placeholder: String,
+    detached_head: DetachedHead,
+    no_upstream_format: Option<Format>,
+    gone_format: Option<Format>,
     debug: bool,
     backend: &'a  Backend,
     value: SimpleValue,
-    values: Vec<SimpleValue>,
+    // the yaml is kept for the options specific to a value type
+    values: Vec<(SimpleValue, Yaml)>,
 
 }
 
@@ -192,23 +1301,36 @@ pub struct RemoteTracking<'a> {
 impl<'a> RemoteTracking<'a> {
     fn new(value_yaml: &Yaml, simple_value: &SimpleValue,
            backend: &'a Backend, debug: bool) -> RemoteTracking<'a> {
-        let remote_branch: Option<RemoteBranch> = match value_yaml["remote_branch"].as_str() {
-            Some(s) => {
-                let remote_branch_string = s.to_string();
-                let v: Vec<&str> = remote_branch_string.splitn(2, '/').collect();
-                if v.len() != 2 {
-                    panic!("`remote_branch` must be in form of `<REMOTE>/<BRANCH>`");
+        let remote_branch: Vec<String> = get_string_list(value_yaml, "remote_branch")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| {
+                // fail early, not only when the branch is looked up
+                if s != DEFAULT_BRANCH_KEYWORD {
+                    parse_remote_branch(&s);
                 }
-                Some(RemoteBranch{
-                    remote_branch: remote_branch_string.clone(),
-                    remote_name: v[0].to_string(),
-                    remote_branch_name: v[1].to_string()
-                })
-            },
-            None => {
-                // log!(self, "remote_branch is not specified: {:?}", value_yaml);
-                None
+                s
+            })
+            .collect();
+        let group_separator = value_yaml["group_separator"].as_str().unwrap_or("").to_string();
+        let max_items = match value_yaml["max_items"].as_i64() {
+            Some(m) if m > 0 => Some(m as usize),
+            Some(m) => panic!("`max_items` needs to be a positive number, not {}", m),
+            None => None,
+        };
+        let candidates = RemoteCandidates::new(value_yaml);
+        let target = match value_yaml["target"].as_str() {
+            Some("upstream") | None => CompareTarget::Upstream,
+            Some("push") => CompareTarget::Push,
+            Some(s) => panic!("`target` needs to be one of 'upstream' or 'push', not '{}'", s),
+        };
+        let walk = WalkOptions{
+            limit: match value_yaml["walk_limit"].as_i64() {
+                Some(l) if l > 0 => Some(l as usize),
+                Some(l) => panic!("`walk_limit` needs to be a positive number, not {}", l),
+                None => None,
             },
+            cache: value_yaml["cache"].as_bool().unwrap_or(false),
         };
         let display_if_uptodate = match value_yaml["display_if_uptodate"].as_bool() {
             Some(b) => b,
@@ -218,11 +1340,12 @@ impl<'a> RemoteTracking<'a> {
             Some(s) => s.to_string(),
             None => NO_REMOTE_PLACEHOLDER.to_string(),
         };
-        let mut values: Vec<SimpleValue> = Vec::new();
+        let detached_head = DetachedHead::new(value_yaml);
+        let mut values: Vec<(SimpleValue, Yaml)> = Vec::new();
         match value_yaml["values"].as_vec() {
             Some(v) => {
                 for inner_value_yaml in v {
-                    values.push(SimpleValue::new(inner_value_yaml));
+                    values.push((SimpleValue::new(inner_value_yaml), inner_value_yaml.clone()));
                 }
             },
             None => panic!("values is empty: {:?}", value_yaml),
@@ -230,11 +1353,21 @@ impl<'a> RemoteTracking<'a> {
         RemoteTracking{
             value: simple_value.clone(), backend: backend, debug: debug,
             display_if_uptodate: display_if_uptodate, values: values,
-            remote_branch: remote_branch, no_remote_placeholder: no_remote_placeholder
+            remote_branch: remote_branch, no_remote_placeholder: no_remote_placeholder,
+            detached_head, target, walk, candidates, group_separator, max_items,
+            no_upstream_format: Format::new(value_yaml, "no_upstream_format"),
+            gone_format: Format::new(value_yaml, "gone_format"),
         }
     }
 
-    fn display_name(&self, value: &SimpleValue, special_values: HashMap<String, String>) -> Option<String> {
+    fn display_name(&self, value: &SimpleValue, value_yaml: &Yaml,
+                    mut special_values: HashMap<String, String>) -> Option<String> {
+        let name_format = BranchNameFormat::new(value_yaml);
+        for key in &["<LOCAL_BRANCH>", "<REMOTE_BRANCH>"] {
+            if let Some(name) = special_values.get_mut(*key) {
+                *name = name_format.apply(name);
+            }
+        }
         Some(format_value(
             &substiute_special_values(value.pre_format.clone(), &special_values),
             &substiute_special_values(value.post_format.clone(), &special_values),
@@ -242,45 +1375,81 @@ impl<'a> RemoteTracking<'a> {
         ))
     }
 
-    fn display_ahead(&self, value: &SimpleValue, ahead: usize) -> Option<String> {
-        if ahead > 0 {
-            return Some(format_value(&value.pre_format, &value.post_format, &ahead.to_string()));
-        }
-        None
-    }
-
-    fn display_behind(&self, value: &SimpleValue, behind: usize) -> Option<String> {
-        if behind > 0 {
-            return Some(format_value(&value.pre_format, &value.post_format, &behind.to_string()));
-        }
-        None
+    // ahead or behind; capped when counting stopped at the walk limit
+    fn display_count(&self, value: &SimpleValue, value_yaml: &Yaml, count: usize, capped: bool)
+            -> Option<String> {
+        NumberFormat::new(value_yaml).display(&Format::from_value(value), count, capped, &HashMap::new())
     }
 
-    fn display_value(&self, simple_value: SimpleValue, a_b: BranchAheadBehind,
+    fn display_value(&self, simple_value: SimpleValue, value_yaml: &Yaml, a_b: BranchAheadBehind,
                      special_values: HashMap<String, String>) -> Option<String> {
         if !(self.display_if_uptodate || a_b.ahead > 0 || a_b.behind > 0) {
             return None;
         }
         match simple_value.value_type.as_str() {
-            "name" => self.display_name(&simple_value, special_values),
-            "ahead" => self.display_ahead(&simple_value, a_b.ahead),
-            "behind" => self.display_behind(&simple_value, a_b.behind),
+            "name" => self.display_name(&simple_value, value_yaml, special_values),
+            "ahead" => self.display_count(&simple_value, value_yaml, a_b.ahead, a_b.ahead_capped),
+            "behind" => self.display_count(&simple_value, value_yaml, a_b.behind, a_b.behind_capped),
             _ => panic!("Unknown value for remote_difference: {:?}", simple_value),
         }
     }
 
+    // the remote branches to compare against, None stands for the one the current branch follows
+    fn get_remote_branches(&self) -> Vec<Option<RemoteBranch>> {
+        if self.remote_branch.is_empty() {
+            return vec!(None);
+        }
+        let mut remote_branches: Vec<Option<RemoteBranch>> = Vec::new();
+        for spec in &self.remote_branch {
+            let found = if is_glob(spec) {
+                self.backend.find_remote_branches(spec)
+            } else {
+                vec!(self.candidates.resolve(self.backend, spec))
+            };
+            for b in found {
+                let is_duplicate = remote_branches.iter().any(|seen| match *seen {
+                    Some(ref s) => s.remote_branch == b.remote_branch,
+                    None => false,
+                });
+                if !is_duplicate {
+                    remote_branches.push(Some(b));
+                }
+            }
+        }
+        if let Some(max_items) = self.max_items {
+            remote_branches.truncate(max_items);
+        }
+        remote_branches
+    }
+
     fn display(&self) -> Option<String> {
         log!(self, "display remote_difference: {:?}", self);
+        let groups: Vec<String> = self.get_remote_branches().into_iter()
+            .filter_map(|b| self.display_group(b))
+            .collect();
+        if groups.is_empty() {
+            None
+        } else {
+            Some(groups.join(&self.group_separator))
+        }
+    }
 
+    // name, ahead and behind for a single remote branch
+    fn display_group(&self, remote_branch: Option<RemoteBranch>) -> Option<String> {
         let a_b: BranchAheadBehind = match self.backend.get_branch_ahead_behind(
-            self.remote_branch.clone()) {
+            remote_branch.clone(), self.target, &self.walk) {
             Some(x) => x,
             None => {
-                log!(self, "no ahead behind stats found for = {:?}", self.remote_branch);
+                log!(self, "no ahead behind stats found for = {:?}", remote_branch);
                 return None;
             },
         };
-        let local_branch_name: String = match a_b.local_branch_name.clone() {
+        let branch_name = if self.backend.is_head_detached() {
+            self.detached_head.name(self.backend)
+        } else {
+            a_b.local_branch_name.clone()
+        };
+        let local_branch_name: String = match branch_name {
             Some(l) => l,
             None => {
                 log!(self, "No local branch name.");
@@ -289,6 +1458,7 @@ impl<'a> RemoteTracking<'a> {
         };
         let mut special_values: HashMap<String, String> = HashMap::new();
         special_values.insert("<LOCAL_BRANCH>".to_string(), local_branch_name.clone());
+        special_values.insert("<DETACHED>".to_string(), self.detached_head.marker(self.backend));
         match a_b.remote_branch_name.clone() {
             Some(v) => special_values.insert("<REMOTE_BRANCH>".to_string(), v),
             None => special_values.insert("<REMOTE_BRANCH>".to_string(), "".to_string()),
@@ -300,10 +1470,25 @@ impl<'a> RemoteTracking<'a> {
         };
         special_values.insert("<REMOTE_FIRST_LETTER>".to_string(), remote_first_letter);
         special_values.insert("<REMOTE>".to_string(), remote_name);
+        let (upstream_state, state_format) = match a_b.upstream_state {
+            UpstreamState::Tracking => ("", None),
+            UpstreamState::Gone => ("gone", self.gone_format.as_ref()),
+            UpstreamState::NoUpstream => ("none", self.no_upstream_format.as_ref()),
+        };
+        special_values.insert("<UPSTREAM_STATE>".to_string(), upstream_state.to_string());
+
+        // the state is worth displaying even if there is no difference
+        if let Some(f) = state_format {
+            return Some(format_value(
+                &substiute_special_values(f.pre_format.clone(), &special_values),
+                &substiute_special_values(f.post_format.clone(), &special_values),
+                ""
+            ));
+        }
 
         let mut response: String = "".to_string();
-        for value in self.values.clone() {
-            if let Some(s) = self.display_value(value.clone(), a_b.clone(), special_values.clone()) {
+        for (value, value_yaml) in self.values.clone() {
+            if let Some(s) = self.display_value(value, &value_yaml, a_b.clone(), special_values.clone()) {
                 response += &s
             }
         }
@@ -320,25 +1505,54 @@ impl<'a> RemoteTracking<'a> {
 // -- a true master
 pub struct DisplayMaster {
     backend: Backend,
+    // the shell the prompt is for, values which emit escape sequences of their own need it
+    shell: Shell,
     debug: bool,
 }
 
 impl DisplayMaster {
     pub fn new(backend: Backend, debug: bool) -> DisplayMaster {
-        DisplayMaster { backend: backend, debug: debug }
+        DisplayMaster { backend: backend, shell: Shell::detect().unwrap_or(Shell::Bash), debug: debug }
+    }
+
+    pub fn set_shell(&mut self, shell: Shell) {
+        self.shell = shell;
+    }
+
+    // the file counts don't need to look into submodules
+    pub fn exclude_submodules(&mut self) {
+        self.backend.set_exclude_submodules(true);
+    }
+
+    pub fn get_shell(&self) -> Shell {
+        self.shell
     }
 
     pub fn display_value(&mut self, value_yaml: &Yaml, simple_value: &SimpleValue) -> Option<String> {
         let o: Option<String> = match simple_value.value_type.as_str() {
             "repository_state" => RepoStatus::new(simple_value, &self.backend, self.debug).display(),
+            "branch" => BranchStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
             "tag" => TagStatus::new(simple_value, &self.backend, self.debug).display(),
+            "head_commit" => HeadCommitStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "fork_point" => ForkPointStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "unpushed_tags" => UnpushedTagsStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "submodules" => SubmodulesStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "worktree" => WorktreeStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "repo_flags" => RepoFlagsStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "remote_host" => RemoteHostStatus::new(value_yaml, simple_value, self.shell, &self.backend, self.debug).display(),
+            "git_config" => GitConfigStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "repo_name" => RepoNameStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "repo_path" => RepoPathStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
+            "lfs" => LfsStatus::new(simple_value, &self.backend, self.debug).display(),
+            "describe" => DescribeStatus::new(value_yaml, simple_value, &self.backend, self.debug).display(),
             "new" |
             "changed" |
             "staged" |
-            "conflicts" => FileStatus::new(simple_value, &mut self.backend, self.debug).display(),
+            "conflicts" => FileStatus::new(value_yaml, simple_value, &mut self.backend, self.debug).display(),
+            "clean" => CleanStatus::new(value_yaml, simple_value, &mut self.backend, self.debug).display(),
             // separator is displayed in conf, pretty hacky
             // "separator" => Separator::new(&simple_value, self.debug).display(),
-            "stash" => StashStatus::new(simple_value, &mut self.backend, self.debug).display(),
+            "stash" => StashStatus::new(value_yaml, simple_value, &mut self.backend, self.debug).display(),
             "remote_difference" => RemoteTracking::new(value_yaml, simple_value, &self.backend, self.debug).display(),
             _ => {
                 // let's ignore these values
@@ -5,11 +5,12 @@
 use std::collections::HashMap;
 use std::io::{self, Write};
 
-use backend::{Backend,DemoData};
+use backend::{Backend,DemoData,Description,StashInfo};
 use colors::{Shell,render};
 use conf::Conf;
 use constants::*;
 use models::DisplayMaster;
+use util::now;
 
 use yaml_rust::Yaml;
 
@@ -36,12 +37,25 @@ fn scenarios() -> Vec<(&'static str, DemoData)> {
     diverged.behind = 1;
     result.push(("diverged from the remote branch", diverged));
 
+    // large enough to reach the thresholds of a value
+    let mut far_behind = DemoData::new("master");
+    far_behind.behind = 64;
+    far_behind.file_statuses = statuses(&[(NEW_KEY, 7), (CHANGED_KEY, 24)]);
+    result.push(("many changes, far behind the remote", far_behind));
+
     let mut no_remote = DemoData::new("feature");
     no_remote.remote_name = None;
     result.push(("branch without a remote counterpart", no_remote));
 
+    let mut gone = DemoData::new("feature");
+    gone.remote_name = None;
+    gone.upstream_gone = true;
+    result.push(("remote branch is gone", gone));
+
     let mut tagged = DemoData::new("master");
     tagged.tag = Some(String::from("0.3.0"));
+    tagged.description = Some(Description{
+        tag: String::from("0.3.0"), distance: 0, hash: String::from("2a4b9c1") });
     result.push(("tag pointing at HEAD", tagged));
 
     let mut conflict = DemoData::new("master");
@@ -50,10 +64,14 @@ fn scenarios() -> Vec<(&'static str, DemoData)> {
     result.push(("conflict during a merge", conflict));
 
     let mut stashed = DemoData::new("master");
-    stashed.stash_count = 2;
+    stashed.stashes = vec!(
+        StashInfo{ branch: Some(String::from("master")), time: now() - 2 * 3600 },
+        StashInfo{ branch: Some(String::from("feature")), time: now() - 3 * 24 * 3600 },
+    );
     result.push(("stashed changes", stashed));
 
     let mut detached = DemoData::new("2a4b9c1");
+    detached.detached = true;
     detached.remote_name = None;
     detached.file_statuses = statuses(&[(CHANGED_KEY, 1)]);
     result.push(("detached HEAD", detached));
@@ -61,8 +79,9 @@ fn scenarios() -> Vec<(&'static str, DemoData)> {
     result
 }
 
-fn render_config(yaml: &Yaml, backend: Backend, debug: bool) -> String {
-    let dm: DisplayMaster = DisplayMaster::new(backend, debug);
+fn render_config(yaml: &Yaml, backend: Backend, shell: Shell, debug: bool) -> String {
+    let mut dm: DisplayMaster = DisplayMaster::new(backend, debug);
+    dm.set_shell(shell);
     let mut conf = Conf::new(yaml.clone(), dm);
     conf.populate_values()
 }
@@ -74,7 +93,7 @@ pub fn preview_demo<W: Write>(out: &mut W, yaml: &Yaml, shell: Shell, colors: bo
     let label_width = all.iter().map(|&(label, _)| label.chars().count()).max().unwrap_or(0);
     for (label, demo) in all {
         let backend = Backend::new_demo(demo, debug);
-        let value = render_config(yaml, backend, debug);
+        let value = render_config(yaml, backend, shell, debug);
         writeln!(out, "  {:width$}  {}", label, render(&value, shell, colors), width = label_width)?;
     }
     Ok(())
@@ -83,7 +102,7 @@ pub fn preview_demo<W: Write>(out: &mut W, yaml: &Yaml, shell: Shell, colors: bo
 // print the prompt for the repository we are in
 pub fn preview_repo<W: Write>(out: &mut W, yaml: &Yaml, backend: Backend, shell: Shell,
                               colors: bool, debug: bool) -> io::Result<()> {
-    let value = render_config(yaml, backend, debug);
+    let value = render_config(yaml, backend, shell, debug);
     writeln!(out, "{}", render(&value, shell, colors))
 }
 
@@ -145,7 +164,9 @@ values:
             ("clean repository", "master"),
             ("new, changed and staged files", "masterΔ2"),
             ("diverged from the remote branch", "feature↑2↓1"),
+            ("many changes, far behind the remote", "master↓64Δ24"),
             ("branch without a remote counterpart", "feature"),
+            ("remote branch is gone", "feature"),
             ("tag pointing at HEAD", "master"),
             ("conflict during a merge", "merge│masterΔ1"),
             ("stashed changes", "master☐2"),
@@ -13,3 +13,258 @@ macro_rules! log {
         }
     })
 }
+
+// how long ago something happened, in the largest unit which fits: 45s, 3h, 2d
+pub fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let units: [(i64, &str); 5] = [
+        (365 * 24 * 3600, "y"),
+        (24 * 3600, "d"),
+        (3600, "h"),
+        (60, "m"),
+        (1, "s"),
+    ];
+    for &(length, unit) in units.iter() {
+        if seconds >= length {
+            return format!("{}{}", seconds / length, unit);
+        }
+    }
+    String::from("0s")
+}
+
+// '90' (seconds), '30m', '1h', '2d', '1w' into seconds
+pub fn parse_duration(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (number, multiplier) = match s.chars().last()? {
+        's' => (&s[..s.len() - 1], 1),
+        'm' => (&s[..s.len() - 1], 60),
+        'h' => (&s[..s.len() - 1], 3600),
+        'd' => (&s[..s.len() - 1], 24 * 3600),
+        'w' => (&s[..s.len() - 1], 7 * 24 * 3600),
+        _ => (s, 1),
+    };
+    number.trim().parse::<i64>().ok().map(|n| n * multiplier)
+}
+
+// where a remote repository is hosted, according to its URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteUrl {
+    pub host: String,
+    // a user, an organization or a group with subgroups: everything but the last component
+    pub owner: String,
+    pub repo: String,
+}
+
+// https://host/owner/repo.git, ssh://user@host:22/owner/repo, user@host:owner/repo.git;
+// None for paths and URLs without both an owner and a repository
+pub fn parse_remote_url(url: &str) -> Option<RemoteUrl> {
+    let (authority, path) = if let Some(i) = url.find("://") {
+        let rest = &url[i + 3..];
+        match rest.find('/') {
+            Some(j) => (&rest[..j], &rest[j + 1..]),
+            None => return None,
+        }
+    } else {
+        // scp-like syntax, a ':' before the first '/'
+        let colon = url.find(':')?;
+        if url[..colon].contains('/') {
+            return None;
+        }
+        (&url[..colon], &url[colon + 1..])
+    };
+    let host = authority.rsplit('@').next()?;
+    let host = match host.find(':') {
+        Some(i) => &host[..i],
+        None => host,
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let slash = path.rfind('/')?;
+    let (owner, repo) = (&path[..slash], &path[slash + 1..]);
+    if host.is_empty() || owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some(RemoteUrl{ host: host.to_lowercase(), owner: owner.to_string(), repo: repo.to_string() })
+}
+
+// a path of a URL with every segment percent-encoded, the '/' between them are kept
+pub fn encode_url_path(path: &str) -> String {
+    let segments: Vec<String> = path.split('/').map(|segment| {
+        segment.bytes().map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        }).collect()
+    }).collect();
+    segments.join("/")
+}
+
+// reads the encoding of Go's encoding/gob
+struct GobReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> GobReader<'a> {
+    // a byte below 128 is the number itself, otherwise it's the negated count of the
+    // big-endian bytes which follow
+    fn uint(&mut self) -> Option<u64> {
+        let first = *self.data.get(self.pos)?;
+        self.pos += 1;
+        if first < 0x80 {
+            return Some(u64::from(first));
+        }
+        let count = (first as i8).wrapping_neg() as usize;
+        if count > 8 {
+            return None;
+        }
+        let mut n: u64 = 0;
+        for b in self.bytes(count)? {
+            n = n << 8 | u64::from(*b);
+        }
+        Some(n)
+    }
+
+    // the lowest bit tells whether the rest is complemented
+    fn int(&mut self) -> Option<i64> {
+        let u = self.uint()?;
+        Some(if u & 1 == 1 { !(u >> 1) as i64 } else { (u >> 1) as i64 })
+    }
+
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(count)?;
+        let b = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(b)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.uint()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec()).ok()
+    }
+}
+
+// keys of a map[string]interface{} which is the last value of a gob stream, the way git-lfs
+// stores its lock cache; None when the data is something else
+pub fn gob_map_keys(data: &[u8]) -> Option<Vec<String>> {
+    let mut stream = GobReader{ data, pos: 0 };
+    let mut last_value: Option<&[u8]> = None;
+    // messages: the length, the id of the type and a value, or a definition of a type when
+    // the id is negative
+    while stream.pos < data.len() {
+        let length = stream.uint()? as usize;
+        let message = stream.bytes(length)?;
+        let mut m = GobReader{ data: message, pos: 0 };
+        if m.int()? >= 0 {
+            last_value = Some(&message[m.pos..]);
+        }
+    }
+    let mut value = GobReader{ data: last_value?, pos: 0 };
+    // a value which is not a struct is sent as a struct with a single field, number 0
+    if value.uint()? != 0 {
+        return None;
+    }
+    let mut keys: Vec<String> = Vec::new();
+    for _ in 0..value.uint()? {
+        keys.push(value.string()?);
+        // the name of the concrete type, nothing follows for nil
+        if !value.string()?.is_empty() {
+            value.int()?;
+            // the value comes with its length, it's not needed
+            let length = value.uint()? as usize;
+            value.bytes(length)?;
+        }
+    }
+    if value.pos != value.data.len() {
+        return None;
+    }
+    Some(keys)
+}
+
+// seconds since the epoch
+pub fn now() -> i64 {
+    match ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use util::{encode_url_path,format_age,gob_map_keys,parse_duration,parse_remote_url,RemoteUrl};
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(-5), "0s");
+        assert_eq!(format_age(0), "0s");
+        assert_eq!(format_age(59), "59s");
+        assert_eq!(format_age(3 * 3600 + 1800), "3h");
+        assert_eq!(format_age(2 * 24 * 3600), "2d");
+        assert_eq!(format_age(400 * 24 * 3600), "1y");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("30m"), Some(1800));
+        assert_eq!(parse_duration("1h"), Some(3600));
+        assert_eq!(parse_duration("2d"), Some(2 * 24 * 3600));
+        assert_eq!(parse_duration("an hour"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_parse_remote_url() {
+        let expected = Some(RemoteUrl{ host: "github.com".to_string(), owner: "TomasTomecek".to_string(),
+                                       repo: "pretty-git-prompt".to_string() });
+        assert_eq!(parse_remote_url("https://github.com/TomasTomecek/pretty-git-prompt.git"), expected);
+        assert_eq!(parse_remote_url("git@github.com:TomasTomecek/pretty-git-prompt.git"), expected);
+        assert_eq!(parse_remote_url("ssh://git@GitHub.com:22/TomasTomecek/pretty-git-prompt/"), expected);
+        let nested = parse_remote_url("https://gitlab.example.com/group/subgroup/project").unwrap();
+        assert_eq!(nested.owner, "group/subgroup");
+        assert_eq!(nested.repo, "project");
+        assert_eq!(parse_remote_url("/srv/git/project.git"), None);
+        assert_eq!(parse_remote_url("../project"), None);
+        assert_eq!(parse_remote_url("https://example.com/project"), None);
+    }
+    #[test]
+    fn test_encode_url_path() {
+        assert_eq!(encode_url_path("feature/JIRA-1_fix.v2~1"), "feature/JIRA-1_fix.v2~1");
+        assert_eq!(encode_url_path("fix#12/100% done"), "fix%2312/100%25%20done");
+        assert_eq!(encode_url_path("čaj"), "%C4%8Daj");
+    }
+    #[test]
+    fn test_gob_map_keys() {
+        fn message(type_id: &[u8], payload: &[u8]) -> Vec<u8> {
+            let mut m = vec!((type_id.len() + payload.len()) as u8);
+            m.extend_from_slice(type_id);
+            m.extend_from_slice(payload);
+            m
+        }
+        fn string(s: &str) -> Vec<u8> {
+            let mut b = vec!(s.len() as u8);
+            b.extend_from_slice(s.as_bytes());
+            b
+        }
+        // what git-lfs writes: the version of the store, then the map from paths and ids to locks
+        let mut map: Vec<u8> = vec!(0, 2);
+        for key in &["design.psd", "*id*://1"] {
+            map.extend(string(key));
+            map.extend(string("*locking.Lock"));
+            // type id 66, a message of 3 bytes
+            map.extend_from_slice(&[0xff, 0x84, 3, 1, 2, 3]);
+        }
+        let mut data: Vec<u8> = Vec::new();
+        // a definition of a type, id -65 doesn't fit a byte
+        data.extend(message(&[0xff, 0x81], &[9, 9, 9]));
+        // the version, int 1
+        data.extend(message(&[4], &[0, 2]));
+        // the map, type id 65
+        data.extend(message(&[0xff, 0x82], &map));
+        assert_eq!(gob_map_keys(&data), Some(vec!("design.psd".to_string(), "*id*://1".to_string())));
+
+        assert_eq!(gob_map_keys(&data[..data.len() - 1]), None);
+        assert_eq!(gob_map_keys(b"not a gob"), None);
+        assert_eq!(gob_map_keys(&[]), None);
+    }
+}
@@ -106,6 +106,19 @@ def test_merge_conflict(tmpdir):
         assert r.run() == "merge│master↑1│✖1"
 
 
+def test_merge_conflict_files(tmpdir):
+    config = """\
+---
+version: '1'
+values:
+    - type: conflicts
+      pre_format: '<FILES> '
+      post_format: ' <BOTH_MODIFIED>'"""
+    with MergeConflict(tmpdir) as r:
+        assert r.run(custom_config_content=config) == "file.txt 1 1"
+        assert r.run(args=["conflicts"]) == "both modified:   file.txt"
+
+
 def test_stashed(tmpdir):
     with SimpleRepoWithStashedContent(tmpdir) as r:
         assert r.run() == "master│☐1"